@@ -0,0 +1,46 @@
+use std::{env, fs, path::{Path, PathBuf}, process};
+
+#[path = "src/shader.rs"]
+mod shader;
+
+// Compiled shaders end up in $OUT_DIR/shaders, where they're embedded from
+const SHADER_SRC: &str = "res/shaders/src";
+
+fn main() {
+    let src_dir = Path::new(SHADER_SRC);
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("Error getting OUT_DIR")).join("shaders");
+    fs::create_dir_all(&out_dir).expect("Error creating shader output directory");
+
+    // Covers includes too, cargo checks every file under a directory
+    println!("cargo:rerun-if-changed={}", SHADER_SRC);
+    println!("cargo:rerun-if-changed=src/shader.rs");
+
+    let mut failed = false;
+    for entry in fs::read_dir(src_dir).expect("Error reading shader source directory") {
+        let path = entry.expect("Error reading shader source directory").path();
+        if shader::kind(&path).is_none() {
+            continue;
+        }
+
+        match shader::compile(&path) {
+            Ok(compiled) => {
+                for warning in &compiled.warnings {
+                    println!("cargo:warning={}", warning);
+                }
+
+                let out = out_dir.join(format!("{}.spv", path.file_name().unwrap().to_string_lossy()));
+                fs::write(&out, compiled.spirv).expect("Error writing compiled shader");
+            },
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}", error);
+                }
+                failed = true;
+            },
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -1,10 +1,24 @@
 #version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include "shading.glsl"
 
 layout(location = 0) in vec3 norm;
+layout(location = 1) flat in uint shading;
 
 layout(location = 0) out vec4 out_col;
 
+const vec3 LIGHT_DIR = normalize(vec3(0.4, 1.0, -0.6));
+const vec3 BASE_COL = vec3(0.8, 0.78, 0.75);
+
 void main() 
 {
-	out_col = vec4(norm * 0.5 + 0.5, 1.0);
+	if (shading == SHADING_LIT) {
+		float diffuse = max(dot(normalize(norm), LIGHT_DIR), 0.0);
+		out_col = vec4(BASE_COL * (0.15 + 0.85 * diffuse), 1.0);
+	} else if (shading == SHADING_UNLIT) {
+		out_col = vec4(BASE_COL, 1.0);
+	} else {
+		out_col = vec4(norm * 0.5 + 0.5, 1.0);
+	}
 }
@@ -2,6 +2,7 @@
 
 layout(push_constant) uniform push_constants {
     mat4 view_proj;
+    uint shading;
 } mats;
 
 layout(std140, set=0, binding=0) readonly buffer MeshData {
@@ -12,8 +13,10 @@ layout(location = 0) in vec3 pos;
 layout(location = 1) in vec3 norm;
 
 layout(location = 0) out vec3 f_norm;
+layout(location = 1) flat out uint f_shading;
 
 void main() {
     gl_Position = mats.view_proj * mesh_data.transforms[gl_InstanceIndex] * vec4(pos, 1);
     f_norm = norm;
-}
\ No newline at end of file
+    f_shading = mats.shading;
+}
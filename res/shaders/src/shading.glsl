@@ -0,0 +1,4 @@
+// Must match ShadingMode in objects/mesh.rs
+const uint SHADING_NORMALS = 0u;
+const uint SHADING_LIT = 1u;
+const uint SHADING_UNLIT = 2u;
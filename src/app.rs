@@ -7,13 +7,15 @@ use vrg::{buffer::BufferBuilder, compute_pass::{ComputePassBuilder, ComputePassD
 use vrg::Renderer;
 use vrg::util::frametime::Frametime;
 
-use std::collections::HashMap;
+use std::{thread, time::Duration};
 use ash::vk::{self, Handle, MicromapBuildSizesInfoEXT};
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
-use winit::event::{VirtualKeyCode, ElementState};
+use std::{fs, path::{Path, PathBuf}};
+use std::sync::mpsc::Sender;
+use winit::event::{VirtualKeyCode, ElementState, MouseButton};
 
-use crate::{controller::Controller, gui::Gui};
-use crate::objects::mesh::Mesh;
+use crate::{assets::Assets, benchmark::{self, Benchmark}, camera::{projection::Projection, CameraMode}, cli::Options, settings::Settings, controller::Controller, cursor::Cursor, event::{AppEvent, WindowRequest}, gui::{Gui, GuiFont}, input::{Action, Binding, Input}, panels, profiler, render_stats::{self, RenderStats}, replay::{RecordedEvent, Recorder, RecordingHeader, Replay}};
+use crate::objects::{mesh::{Mesh, ShadingMode, Vertex}, transform::Transform};
 
 #[repr(C)]
 #[repr(align(16))]
@@ -51,63 +53,164 @@ impl Default for ImDrawVertWrapper {
     }
 }
 
+// Logical asset paths
+const DEFAULT_MESH: &str = "meshes/asdf.obj";
+const TEXT_FONT: &str = "fonts/DejaVuSans.ttf";
+
+// Seconds without further changes before settings are written, so dragging a slider doesn't save every frame
+const SETTINGS_SAVE_DELAY: f32 = 0.5;
+
+// Arrows and geometric shapes, merged in from the text font since the default ranges only cover latin
+const SYMBOL_GLYPH_RANGES: [u32; 5] = [0x2190, 0x21ff, 0x25a0, 0x25ff, 0];
+
 #[repr(C)]
 pub struct MeshPushConstant {
     pub view_proj: Mat4,
+    pub shading: u32,
 }
 
 pub struct App {
     pub renderer: Renderer,
+    assets: Assets,
     pub controller: Controller,
+    pub input: Input,
     pub gui: Gui,
+    pub projection: Projection,
 
+    // Window size, what the window reopens at. Anything rendered goes by the renderer's target size instead.
     pub screen_res: Vec2,
+    window_pos: Option<(i32, i32)>,
+    fullscreen: bool,
+    pub minimized: bool,
+    pending_resize: Option<(u32, u32)>,
+
+    pub running: bool,
+    // Process exit status once running is false
+    pub exit_code: i32,
+    pub focused: bool,
+    pub cursor: Cursor,
+    cursor_grabbed: bool,
+    window_requests: Sender<WindowRequest>,
+
+    recorder: Option<Recorder>,
+    // Whether ImGui wanted the keyboard and mouse after its last update. Replays take these from the
+    // recording rather than the live GUI.
+    gui_keyboard: bool,
+    gui_mouse: bool,
+    replay: Option<Replay>,
+    benchmark: Option<Benchmark>,
 
     pub frametime: Frametime,
+    render_stats: RenderStats,
 
-    mesh_push_constant: MeshPushConstant,
+    // Instance centres and the mesh's bounding radius, for counting visible instances
+    instance_positions: Vec<Vec3>,
+    mesh_radius: f32,
+    mesh_vertex_count: usize,
+    mesh_index_count: usize,
 
-    pub ctx: *mut imgui::sys::ImGuiContext,
+    mesh_push_constant: MeshPushConstant,
+    clear_color: [f32; 4],
+    scene: PathBuf,
+
+    // Last saved or loaded, compared against the live values every frame
+    settings: Settings,
+    settings_save_timer: Option<f32>,
+    // Off for benchmarks and replays, whose changes aren't the user's
+    persist_settings: bool,
+    // The window size came from --resolution, so it isn't what the window should reopen at
+    resolution_override: bool,
 }
 
 impl App {
-    pub unsafe fn new(window: RawWindowHandle, display: RawDisplayHandle, r: Vec2) -> App {
+    pub unsafe fn new(window: RawWindowHandle, display: RawDisplayHandle, r: Vec2, scale_factor: f64, window_requests: Sender<WindowRequest>, options: &Options, settings: Settings) -> App {
         let mesh_push_constant = MeshPushConstant {
             view_proj: Mat4::identity(),
+            shading: settings.shading as u32,
         };
 
-        let monkey_mesh = Mesh::from_obj("./res/meshes/asdf.obj");
+        let mut assets = Assets::with_default_search();
+        for archive in &options.archive {
+            assets.mount_archive(archive).unwrap_or_else(|e| panic!("Error: Couldn't mount archive \"{}\": {}", archive.display(), e));
+        }
+
+        // Scenes given on the command line are plain files, the default can come from an archive or the executable. The last
+        // scene may have been moved, deleted or broken since, and falls back to the default.
+        let user_scene = options.mesh.clone().or_else(|| settings.last_scene.clone().filter(|p| p.is_file())).and_then(|path| {
+            match Mesh::from_obj(&path.to_string_lossy()) {
+                // Absolute so the saved last scene still resolves when started from another directory
+                Ok(mesh) => Some((fs::canonicalize(&path).unwrap_or(path), mesh)),
+                Err(e) => {
+                    println!("Error: Couldn't load {}, using the default scene", e);
+                    None
+                },
+            }
+        });
+        let (scene, monkey_mesh) = match user_scene {
+            Some(scene) => scene,
+            None => {
+                let data = assets.read(DEFAULT_MESH).unwrap_or_else(|e| panic!("Error: {}", e));
+                (PathBuf::from(DEFAULT_MESH), Mesh::from_obj_bytes(DEFAULT_MESH, &data).unwrap_or_else(|e| panic!("Error: {}", e)))
+            },
+        };
+
+        let mut renderer = Renderer::new(window, display, !options.no_validation);
+        let gui = Gui::new(&mut renderer, &assets, "base", "gui", &[
+            GuiFont { path: TEXT_FONT.to_string(), size: 15.0, glyph_ranges: None },
+            GuiFont { path: TEXT_FONT.to_string(), size: 15.0, glyph_ranges: Some(&SYMBOL_GLYPH_RANGES) },
+        ], scale_factor as f32, settings.gui_scale);
+
+        let (w, h) = renderer.get_target_size();
+        let projection = Projection::new(w, h);
 
-        let mut renderer = Renderer::new(window, display, true);
-        let gui = Gui::new(&mut renderer, "base", "gui");
-        
         let mut app = App {
             renderer,
+            assets,
             controller: Controller::new(),
+            input: Input::load(),
             gui,
+            projection,
 
             screen_res: r,
+            window_pos: settings.window.x.zip(settings.window.y),
+            fullscreen: options.fullscreen,
+            minimized: false,
+            pending_resize: None,
+
+            running: true,
+            exit_code: 0,
+            focused: true,
+            cursor: Cursor::new(),
+            cursor_grabbed: false,
+            window_requests,
+
+            recorder: None,
+            gui_keyboard: false,
+            gui_mouse: false,
+            replay: None,
+            benchmark: None,
 
             frametime: Frametime::new(),
+            render_stats: RenderStats::new(),
+
+            instance_positions: Vec::new(),
+            mesh_radius: bounding_radius(&monkey_mesh),
+            mesh_vertex_count: monkey_mesh.verts.len(),
+            mesh_index_count: monkey_mesh.indices.len(),
 
             mesh_push_constant,
+            clear_color: settings.clear_color,
+            scene,
 
-            ctx: imgui::sys::igCreateContext(null_mut()),
+            settings: settings.clone(),
+            settings_save_timer: None,
+            persist_settings: options.benchmark.is_none() && options.replay.is_none(),
+            resolution_override: options.resolution.is_some(),
         };
 
-        let cw: usize = 20;
-        let mesh_count: usize = cw * cw * cw;
-        let mut mesh_data = Vec::<Mat4>::with_capacity(mesh_count);
-        for i in 0..cw {
-            for j in 0..cw {
-                for k in 0..cw {
-                    mesh_data.push(Mat4::identity());
-                    mesh_data[i * cw * cw + j * cw + k].w.x = i as f32 * 2.0;
-                    mesh_data[i * cw * cw + j * cw + k].w.y = j as f32 * 2.0;
-                    mesh_data[i * cw * cw + j * cw + k].w.z = k as f32 * 2.0;
-                }
-            }
-        }
+        app.instance_positions = options.layout.positions(options.instances, options.spacing);
+        let mesh_count = app.instance_positions.len();
+        let mesh_data: Vec<Mat4> = app.instance_positions.iter().map(|p| Transform::from_pos(*p).to_mat()).collect();
 
         let storage_buffer = BufferBuilder::new()
             .size(mesh_data.len() * size_of::<Mat4>())
@@ -116,19 +219,23 @@ impl App {
             .properties(vk::MemoryPropertyFlags::DEVICE_LOCAL);
 
         app.renderer.add_buffers("mesh_transforms", storage_buffer, Some(mesh_data.as_ptr()));
+        app.render_stats.track("mesh_transforms", mesh_data.len() * size_of::<Mat4>());
+        app.render_stats.track("mesh_draw vertices", monkey_mesh.verts.len() * size_of::<Vertex>());
+        app.render_stats.track("mesh_draw indices", monkey_mesh.indices.len() * size_of::<u32>());
 
         let mesh_pass_creation_refs = vec![CreationReference::Storage("mesh_transforms".to_string())];
 
         let mesh_pass_builder = GraphicsPassBuilder::new()
-            .vertex_shader("./res/shaders/bin/mesh.vert.spv")
-            .fragment_shader("./res/shaders/bin/mesh.frag.spv")
+            .vertex_shader(&app.assets.shader_path("mesh.vert"))
+            .fragment_shader(&app.assets.shader_path("mesh.frag"))
             .draw_info(GraphicsPassDrawInfo::instanced_indexed(monkey_mesh.verts.len(), monkey_mesh.indices.len(), mesh_count))
             .targets(app.renderer.get_images("swapchain_image"))
             .vertex_descriptors(mesh_pass_creation_refs, &app.renderer.data)
             .verts(&monkey_mesh.verts)
             .vertex_indices(&monkey_mesh.indices)
+            .resizable_vertex_buffer()
             .vertex_push_constant::<MeshPushConstant>()
-            .clear_col(Vec4::new(0.82, 0.8, 0.9, 1.0))
+            .clear_col(Vec4::new(app.clear_color[0], app.clear_color[1], app.clear_color[2], app.clear_color[3]))
             .with_depth_buffer();
 
         app.renderer.add_graphics_pass("base", "mesh_draw", mesh_pass_builder);
@@ -148,44 +255,500 @@ impl App {
         app.renderer.get_layer_mut("base").set_root_path("gui");
         app.renderer.set_root_layer("base");
 
+        app.update_target_stats();
+        app.apply_settings(&settings);
+
+        app.sync_cursor();
+
         app
     }
 
     pub unsafe fn main_loop(&mut self) {
-        let delta = self.frametime.get_delta();
+        // Only the latest size matters when several resizes queue up in one frame
+        if let Some((w, h)) = self.pending_resize.take() {
+            self.resize(w, h);
+        }
+
+        let mut delta = self.frametime.get_delta();
 
         self.frametime.refresh();
+        profiler::begin_frame();
+
+        // Replays substitute the recorded input and delta so the camera path is reproduced exactly
+        if let Some(replay) = &mut self.replay {
+            match replay.next_frame() {
+                Some(frame) => {
+                    delta = frame.delta;
+                    for event in frame.events {
+                        self.replay_event(event.event);
+                    }
+                },
+                None => {
+                    self.running = false;
+                    profiler::end_frame();
+                    return;
+                },
+            }
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_frame(delta);
+        }
 
-        self.update(delta);
+        {
+            let _scope = profiler::scope("Game");
+            self.update(delta);
+        }
         self.frametime.set("Game");
 
-        self.draw();
+        self.sync_settings(delta);
+
+        // There's no swapchain to present to while minimized
+        if self.minimized {
+            profiler::end_frame();
+            thread::sleep(Duration::from_millis(10));
+            return;
+        }
+
+        {
+            let _scope = profiler::scope("Draw");
+            self.draw();
+        }
         self.frametime.set("Draw");
 
+        profiler::end_frame();
+
+        if let Some(benchmark) = &mut self.benchmark {
+            let samples = profiler::with(|p| {
+                let frame = ("frame_ms".to_string(), p.frame_times.back().copied().unwrap_or(0.0));
+                let sections = p.sections.iter().map(|(path, section)| (path.clone(), section.history.back().copied().unwrap_or(0.0)));
+
+                std::iter::once(frame).chain(sections).collect()
+            });
+            benchmark.record(samples);
+
+            if benchmark.done() {
+                if !benchmark.finish() {
+                    self.exit_code = 1;
+                }
+                self.running = false;
+            }
+        }
+
         //println!("{}", self.frametime);
     }
 
+    pub unsafe fn resize(&mut self, w: u32, h: u32) {
+        self.minimized = w == 0 || h == 0;
+        if self.minimized {
+            return;
+        }
+
+        self.screen_res = Vec2::new(w as f32, h as f32);
+
+        // vrg keeps the swapchain it was created with, so the projection goes by that rather than the window. The
+        // GUI and the render stats read the same size from the renderer.
+        let (target_w, target_h) = self.renderer.get_target_size();
+        self.projection.set_target_size(target_w, target_h);
+        self.update_target_stats();
+    }
+
     pub fn update(&mut self, delta: f32) {
-        self.controller.update(delta);
+        self.input.begin_frame();
+        self.gui.delta = delta;
+
+        if self.input.pressed(Action::Quit) {
+            self.running = false;
+        }
+        if self.input.pressed(Action::ToggleCursor) {
+            self.cursor.toggle();
+        }
+        if self.input.pressed(Action::DragLook) {
+            self.cursor.begin_drag();
+        }
+        if self.input.released(Action::DragLook) {
+            self.cursor.end_drag();
+        }
+        self.sync_cursor();
 
-        self.mesh_push_constant.view_proj = (self.controller.view_mat * Mat4::perspective(16.0 / 9.0, PI / 2.0, 0.0005, 100.0)).transpose();
+        self.controller.update(delta, &self.input);
+
+        // The benchmark path overrides whatever camera is active
+        if let Some(benchmark) = &self.benchmark {
+            self.controller.view_mat = benchmark.pose().view_mat();
+        }
+
+        self.mesh_push_constant.view_proj = (self.controller.view_mat * self.projection.mat()).transpose();
     }
 
     pub unsafe fn draw(&mut self) {
-        self.renderer.pre_draw();
-        
+        {
+            let _scope = profiler::scope("Pre draw");
+            self.renderer.pre_draw();
+        }
+
+        let gui_state = self.gui_state();
+        let controller = &mut self.controller;
+        let projection = &mut self.projection;
+        let input = &mut self.input;
+        let render_stats = &self.render_stats;
+        let mut ui_scale = self.gui.ui_scale;
+        let mut settings = self.settings.clone();
+        let mut settings_changed = false;
+        self.gui.update(&mut self.renderer, |ui| {
+            panels::interface_panel(ui, &mut ui_scale);
+            settings_changed = panels::settings_panel(ui, &mut settings);
+            panels::profiler_panel(ui);
+            panels::render_stats_panel(ui, render_stats);
+            panels::camera_panel(ui, controller);
+            panels::projection_panel(ui, projection);
+            panels::controls_panel(ui, input);
+        });
+        self.gui.ui_scale = ui_scale;
+        if settings_changed {
+            self.apply_settings(&settings);
+        }
+        if self.replay.is_none() {
+            self.gui_keyboard = self.gui.want_capture_keyboard();
+            self.gui_mouse = self.gui.want_capture_mouse();
+        }
+        self.record_gui_changes(gui_state);
         self.gui.render(&mut self.renderer);
 
         self.renderer.get_layer_mut("base").fill_vertex_push_constant("mesh_draw", &self.mesh_push_constant);
+        self.update_frame_stats();
 
+        let _scope = profiler::scope("Submit");
         self.renderer.draw();
     }
 
+    // Pushes edited settings out to the systems they control
+    fn apply_settings(&mut self, settings: &Settings) {
+        self.controller.set_speed(settings.camera_speed);
+        self.input.sensitivity = settings.mouse_sensitivity;
+        self.projection.fov = settings.fov_degrees.to_radians();
+        self.gui.ui_scale = settings.gui_scale;
+        self.mesh_push_constant.shading = settings.shading as u32;
+
+        // Only read when the pass is built, so a new clear color shows after a restart
+        self.clear_color = settings.clear_color;
+    }
+
+    // Settings as they currently are, including changes made outside the settings panel
+    fn current_settings(&self) -> Settings {
+        let mut window = self.settings.window.clone();
+
+        // A fullscreen size isn't what the window should reopen at
+        if !self.fullscreen {
+            if !self.resolution_override {
+                window.width = self.screen_res.x as u32;
+                window.height = self.screen_res.y as u32;
+            }
+            if let Some((x, y)) = self.window_pos {
+                window.x = Some(x);
+                window.y = Some(y);
+            }
+        }
+
+        Settings {
+            version: self.settings.version,
+
+            camera_speed: self.controller.speed(),
+            mouse_sensitivity: self.input.sensitivity,
+            fov_degrees: self.projection.fov.to_degrees(),
+
+            clear_color: self.clear_color,
+            shading: ShadingMode::ALL[self.mesh_push_constant.shading as usize],
+            gui_scale: self.gui.ui_scale,
+
+            // The default lives in the assets rather than at a path that can be reopened
+            last_scene: (self.scene != Path::new(DEFAULT_MESH)).then(|| self.scene.clone()),
+
+            window,
+        }
+    }
+
+    // Saves once the settings have stopped changing for a moment
+    fn sync_settings(&mut self, delta: f32) {
+        if !self.persist_settings {
+            return;
+        }
+
+        let current = self.current_settings();
+        if current != self.settings {
+            self.settings = current;
+            self.settings_save_timer = Some(SETTINGS_SAVE_DELAY);
+        }
+
+        if let Some(timer) = &mut self.settings_save_timer {
+            *timer -= delta;
+            if *timer <= 0.0 {
+                self.settings.save();
+                self.settings_save_timer = None;
+            }
+        }
+    }
+
+    // Shown next frame, since the stats panel is built before the GUI's own draws are known
+    fn update_frame_stats(&mut self) {
+        let view_proj = self.controller.view_mat * self.projection.mat();
+        let stats = &mut self.render_stats;
+
+        stats.instances = self.instance_positions.len();
+        stats.visible_instances = render_stats::count_visible(&view_proj, &self.projection.mat(), &self.instance_positions, self.mesh_radius);
+        // Nothing is culled yet, so every instance is drawn
+        stats.triangles = self.mesh_index_count / 3 * stats.instances;
+        stats.vertices = self.mesh_vertex_count * stats.instances;
+        stats.draw_calls = 1 + self.gui.draw_calls;
+
+        stats.gui_draw_calls = self.gui.draw_calls;
+        stats.gui_vertices = self.gui.vertex_count;
+        stats.gui_indices = self.gui.index_count;
+        stats.track("gui_font", self.gui.font_bytes);
+        stats.track("gui vertices", self.gui.vertex_count * Gui::VERTEX_SIZE);
+        stats.track("gui indices", self.gui.index_count * size_of::<u32>());
+    }
+
+    // Reads the target size back from the renderer on startup and resize
+    fn update_target_stats(&mut self) {
+        let (w, h) = self.renderer.get_target_size();
+        let stats = &mut self.render_stats;
+
+        stats.resolution = (w, h);
+        stats.track("swapchain_image", self.renderer.get_images("swapchain_image").len() * (w * h) as usize * 4);
+        stats.track("mesh_draw depth", (w * h) as usize * 4);
+    }
+
+    // Starts with the current state and input mapping so replays don't depend on the settings they're run with
+    pub fn start_recording(&mut self, path: &str) {
+        let mut recorder = Recorder::new(path, RecordingHeader { input: self.input.config() });
+        recorder.push(RecordedEvent::Focused(self.focused));
+        for event in self.gui_state() {
+            recorder.push(event);
+        }
+
+        self.recorder = Some(recorder);
+    }
+
+    pub fn start_replay(&mut self, path: &str) {
+        match Replay::load(path) {
+            Ok(replay) => {
+                self.input = Input::new(replay.header.input.clone());
+                self.replay = Some(replay);
+            },
+            Err(e) => {
+                println!("Error: {}", e);
+                self.exit_code = 1;
+                self.running = false;
+            },
+        }
+    }
+
+    pub fn start_benchmark(&mut self, frames: usize, output: &str, thresholds: Option<&str>) {
+        match thresholds.map(benchmark::load_thresholds).transpose() {
+            Ok(thresholds) => self.benchmark = Some(Benchmark::new(frames, output, thresholds.unwrap_or_default())),
+            Err(e) => {
+                println!("Error: {}", e);
+                self.exit_code = 1;
+                self.running = false;
+            },
+        }
+    }
+
+    pub fn handle_event(&mut self, event: AppEvent) {
+        // Live input would make the replay diverge from the recording, only window changes that don't
+        // affect the simulation go through
+        if self.replay.is_some() && !matches!(event, AppEvent::Resized(..) | AppEvent::Moved(..) | AppEvent::ScaleFactor(_) | AppEvent::Quit) {
+            return;
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&event);
+        }
+
+        // The GUI only sees the mouse while the cursor is free, except for button releases so a press it saw before
+        // the cursor was captured doesn't stay held
+        let mouse_event = matches!(
+            event,
+            AppEvent::MouseButton { state: ElementState::Pressed, .. } | AppEvent::CursorPos(_) | AppEvent::Wheel(_)
+        );
+        if !(mouse_event && self.cursor.captured()) {
+            self.gui.handle_event(&event);
+        }
+
+        match event {
+            AppEvent::Key { key, state } => self.update_key(key, state),
+            // Only the GUI uses these
+            AppEvent::Modifiers(_) | AppEvent::CursorPos(_) | AppEvent::Text(_) => {},
+            AppEvent::MouseButton { button, state } => self.update_button(button, state),
+            AppEvent::MouseMotion(d) => self.update_mouse(d),
+            AppEvent::Wheel(d) => self.update_wheel(d),
+            AppEvent::Focused(f) => self.update_focus(f),
+            AppEvent::Resized(w, h) => self.pending_resize = Some((w, h)),
+            AppEvent::Moved(x, y) => self.window_pos = Some((x, y)),
+            AppEvent::ScaleFactor(s) => self.gui.set_dpi_scale(s as f32),
+            AppEvent::FileDropped(path) => unsafe { self.load_scene(path) },
+            AppEvent::Quit => self.running = false,
+        }
+    }
+
+    // Swaps the instanced mesh for an .obj dropped onto the window
+    unsafe fn load_scene(&mut self, path: PathBuf) {
+        if !path.extension().is_some_and(|e| e.eq_ignore_ascii_case("obj")) {
+            println!("Error: Can't load \"{}\", only .obj scenes are supported", path.display());
+            return;
+        }
+
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(e) => {
+                println!("Error: Couldn't read \"{}\": {}", path.display(), e);
+                return;
+            },
+        };
+
+        let mesh = match Mesh::from_obj_bytes(&path.to_string_lossy(), raw.as_bytes()) {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                println!("Error: Couldn't load {}", e);
+                return;
+            },
+        };
+        if mesh.indices.is_empty() {
+            println!("Error: \"{}\" has no faces", path.display());
+            return;
+        }
+
+        self.renderer.update_vertex_buffer("base", "mesh_draw", Some(&mesh.verts), Some(&mesh.indices));
+        let draw_infos = &mut self.renderer.get_layer_mut("base").get_graphics_pass_mut("mesh_draw").draw_infos;
+        draw_infos.clear();
+        draw_infos.push(GraphicsPassDrawInfo::instanced_indexed(mesh.verts.len(), mesh.indices.len(), self.instance_positions.len()));
+
+        self.mesh_radius = bounding_radius(&mesh);
+        self.mesh_vertex_count = mesh.verts.len();
+        self.mesh_index_count = mesh.indices.len();
+        self.render_stats.track("mesh_draw vertices", mesh.verts.len() * size_of::<Vertex>());
+        self.render_stats.track("mesh_draw indices", mesh.indices.len() * size_of::<u32>());
+        self.scene = fs::canonicalize(&path).unwrap_or(path);
+    }
+
+    // Runs on the app thread once the main loop exits. Frames can still be in flight, so the GPU has to be done with
+    // them before the renderer's resources are destroyed on drop.
+    pub unsafe fn shutdown(self) {
+        self.renderer.device.device.device_wait_idle().expect("Error waiting for the GPU to idle");
+
+        // Don't lose a change made just before quitting
+        if self.persist_settings && self.settings_save_timer.is_some() {
+            self.settings.save();
+        }
+    }
+
+    fn replay_event(&mut self, event: RecordedEvent) {
+        match event {
+            RecordedEvent::Key { key, state } => self.update_key(key, state),
+            RecordedEvent::MouseButton { button, state } => self.update_button(button, state),
+            RecordedEvent::MouseMotion(x, y) => self.update_mouse(Vec2::new(x, y)),
+            RecordedEvent::Wheel(d) => self.update_wheel(d),
+            RecordedEvent::Focused(f) => self.update_focus(f),
+            RecordedEvent::CameraMode(mode) => self.controller.set_mode(mode),
+            RecordedEvent::Controls { speed, sensitivity, invert_y } => {
+                self.controller.set_speed(speed);
+                self.input.sensitivity = sensitivity;
+                self.input.invert_y = invert_y;
+            },
+            RecordedEvent::GuiCapture { keyboard, mouse } => {
+                self.gui_keyboard = keyboard;
+                self.gui_mouse = mouse;
+            },
+        }
+    }
+
+    // GUI state the simulation depends on, in the form it's recorded in
+    fn gui_state(&self) -> [RecordedEvent; 3] {
+        [
+            RecordedEvent::CameraMode(self.controller.mode()),
+            RecordedEvent::Controls { speed: self.controller.speed(), sensitivity: self.input.sensitivity, invert_y: self.input.invert_y },
+            RecordedEvent::GuiCapture { keyboard: self.gui_keyboard, mouse: self.gui_mouse },
+        ]
+    }
+
+    // Changes made through the GUI don't arrive as input events, so they're recorded separately
+    fn record_gui_changes(&mut self, before: [RecordedEvent; 3]) {
+        let after = self.gui_state();
+        if let Some(recorder) = &mut self.recorder {
+            for (before, after) in before.into_iter().zip(after) {
+                if before != after {
+                    recorder.push(after);
+                }
+            }
+        }
+    }
+
+    // Tells the window thread when the cursor's grab state needs to change
+    fn sync_cursor(&mut self) {
+        let grabbed = self.cursor.captured();
+        if grabbed != self.cursor_grabbed {
+            if grabbed {
+                self.gui.clear_mouse();
+            }
+
+            self.cursor_grabbed = grabbed;
+            let _ = self.window_requests.send(WindowRequest::SetCursorGrab(grabbed));
+        }
+    }
+
+    // Presses ImGui wants are kept from the camera, releases always go through so nothing sticks
     pub fn update_key(&mut self, vk: VirtualKeyCode, s: ElementState) {
-        self.controller.keys.insert(vk, s);
+        if !(self.gui_keyboard && s == ElementState::Pressed) {
+            self.update_binding(Binding::Key(vk), s);
+        }
+    }
+
+    fn update_button(&mut self, button: MouseButton, s: ElementState) {
+        if !(self.gui_has_mouse() && s == ElementState::Pressed) {
+            self.update_binding(Binding::Mouse(button), s);
+        }
+    }
+
+    fn update_focus(&mut self, f: bool) {
+        self.focused = f;
+        if !f {
+            self.input.release_all();
+        }
+
+        self.cursor.set_focused(f);
+        self.sync_cursor();
+    }
+
+    // The GUI only gets the mouse while the cursor is free
+    fn gui_has_mouse(&self) -> bool {
+        !self.cursor.captured() && self.gui_mouse
     }
 
     pub fn update_mouse(&mut self, d: Vec2) {
-        self.controller.mouse_pos += d;
+        // Free cursor motion is for the GUI, not the camera
+        if self.cursor.captured() {
+            self.input.handle_motion(d);
+        }
+    }
+
+    pub fn update_wheel(&mut self, d: f32) {
+        if !self.gui_has_mouse() {
+            self.input.handle_wheel(d);
+        }
     }
-}
\ No newline at end of file
+
+    fn update_binding(&mut self, binding: Binding, s: ElementState) {
+        let rebinding = self.input.rebinding.is_some();
+
+        // Persist as soon as a rebind from the GUI completes
+        if self.input.handle_binding(binding, s) && rebinding && self.input.rebinding.is_none() {
+            self.input.save();
+        }
+    }
+}
+
+// Distance from the origin to the furthest vertex, for culling instances in the stats
+fn bounding_radius(mesh: &Mesh) -> f32 {
+    mesh.verts.iter().map(|v| (v.pos.x * v.pos.x + v.pos.y * v.pos.y + v.pos.z * v.pos.z).sqrt()).fold(0.0, f32::max)
+}
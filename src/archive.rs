@@ -0,0 +1,309 @@
+use std::{collections::BTreeMap, fs::{self, File}, io::{self, BufWriter, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use crate::assets::Mount;
+
+// Layout, all integers little endian:
+//
+// header  magic "RPAK", version u32, entry count u32
+// index   per entry: path length u16, path (UTF-8, '/' separated), offset u64, stored size u64, size u64, compressed u8, hash u64
+// data    entry contents back to back, offsets are from the start of this section
+const MAGIC: &[u8; 4] = b"RPAK";
+const VERSION: u32 = 1;
+
+// Smallest possible index entry, one with an empty path
+const MIN_ENTRY_SIZE: u64 = 2 + 8 + 8 + 8 + 1 + 8;
+
+// Entries that don't shrink by at least this fraction are stored uncompressed
+const MIN_SAVING: f32 = 0.05;
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: String,
+    offset: u64,
+    stored_size: u64,
+    pub size: u64,
+    pub compressed: bool,
+    // Of the uncompressed contents, see content_hash
+    pub hash: u64,
+}
+
+pub struct Archive {
+    file: PathBuf,
+    entries: Vec<Entry>,
+    data_start: u64,
+}
+
+impl Archive {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Archive> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an asset archive"));
+        }
+        let version = read_u32(&mut file)?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported archive version {}", version)));
+        }
+
+        // Checked before anything is allocated from it, so a corrupt header can't ask for gigabytes
+        let count = read_u32(&mut file)?;
+        if count as u64 * MIN_ENTRY_SIZE > len - file.stream_position()? {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("index claims {} entries, more than fit in the file", count)));
+        }
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut path = vec![0u8; read_u16(&mut file)? as usize];
+            file.read_exact(&mut path)?;
+
+            entries.push(Entry {
+                path: String::from_utf8(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                offset: read_u64(&mut file)?,
+                stored_size: read_u64(&mut file)?,
+                size: read_u64(&mut file)?,
+                compressed: read_u8(&mut file)? != 0,
+                hash: read_u64(&mut file)?,
+            });
+        }
+
+        let data_start = file.stream_position()?;
+        for entry in &entries {
+            let end = data_start.checked_add(entry.offset).and_then(|start| start.checked_add(entry.stored_size));
+            if !matches!(end, Some(end) if end <= len) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("\"{}\" extends past the end of the file", entry.path)));
+            }
+        }
+
+        // Sorted on build, so lookups can binary search
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Archive {
+            file: path.to_path_buf(),
+            data_start,
+            entries,
+        })
+    }
+
+    // Packs files keyed by logical path, see collect
+    pub fn build<Q: AsRef<Path>>(files: &BTreeMap<String, Vec<u8>>, out: Q, compress: bool) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::with_capacity(files.len());
+        let mut blobs = Vec::with_capacity(files.len());
+        let mut offset = 0;
+
+        for (path, data) in files {
+            let size = data.len() as u64;
+            let hash = content_hash(data);
+
+            let packed = if compress { Some(deflate(data)?) } else { None };
+            let (blob, compressed) = match packed {
+                Some(packed) if (packed.len() as f32) < data.len() as f32 * (1.0 - MIN_SAVING) => (packed, true),
+                _ => (data.clone(), false),
+            };
+
+            entries.push(Entry {
+                path: path.clone(),
+                offset,
+                stored_size: blob.len() as u64,
+                size,
+                compressed,
+                hash,
+            });
+            offset += blob.len() as u64;
+            blobs.push(blob);
+        }
+
+        let mut out = BufWriter::new(File::create(out)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(entries.len() as u32).to_le_bytes())?;
+        for entry in &entries {
+            out.write_all(&(entry.path.len() as u16).to_le_bytes())?;
+            out.write_all(entry.path.as_bytes())?;
+            out.write_all(&entry.offset.to_le_bytes())?;
+            out.write_all(&entry.stored_size.to_le_bytes())?;
+            out.write_all(&entry.size.to_le_bytes())?;
+            out.write_all(&[entry.compressed as u8])?;
+            out.write_all(&entry.hash.to_le_bytes())?;
+        }
+        for blob in &blobs {
+            out.write_all(blob)?;
+        }
+        out.flush()?;
+
+        Ok(entries)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, path: &str) -> Option<&Entry> {
+        self.entries.binary_search_by(|e| e.path.as_str().cmp(path)).ok().map(|i| &self.entries[i])
+    }
+
+    // Decompresses the entry and checks it against its hash
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entry(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("\"{}\" isn't in the archive", path)))?;
+
+        let mut file = File::open(&self.file)?;
+        file.seek(SeekFrom::Start(self.data_start + entry.offset))?;
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        file.read_exact(&mut stored)?;
+
+        let data = if entry.compressed {
+            // The size comes from the index, so it only bounds the output rather than sizing an allocation up front
+            let mut data = Vec::new();
+            DeflateDecoder::new(&stored[..]).take(entry.size).read_to_end(&mut data)?;
+            data
+        } else {
+            stored
+        };
+
+        if content_hash(&data) != entry.hash {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("\"{}\" is corrupt, its hash doesn't match the index", path)));
+        }
+
+        Ok(data)
+    }
+
+    // Entries whose counterpart in files has changed or been removed since the archive was built
+    pub fn stale_entries(&self, files: &BTreeMap<String, Vec<u8>>) -> Vec<&Entry> {
+        self.entries.iter().filter(|e| {
+            match files.get(&e.path) {
+                Some(data) => content_hash(data) != e.hash,
+                None => true,
+            }
+        }).collect()
+    }
+}
+
+impl Mount for Archive {
+    // Archived files have no path on disk
+    fn path(&self, _logical: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn read(&self, logical: &str) -> Option<Vec<u8>> {
+        self.entry(logical)?;
+
+        Archive::read(self, logical).map_err(|e| println!("Error: Couldn't read \"{}\" from \"{}\": {}", logical, self.file.display(), e)).ok()
+    }
+
+    fn describe(&self) -> String {
+        format!("{} (archive)", self.file.display())
+    }
+}
+
+// 64 bit FNV-1a, enough to tell whether a file changed
+pub fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn deflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+// Every file under root, keyed by its path relative to root
+pub fn collect<P: AsRef<Path>>(root: P) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let root = root.as_ref();
+    let mut paths = Vec::new();
+    collect_files(root, &mut paths)?;
+
+    let mut files = BTreeMap::new();
+    for file in paths {
+        let path = file.strip_prefix(root).unwrap().components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        files.insert(path, fs::read(&file)?);
+    }
+
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u16<R: Read>(r: &mut R) -> io::Result<u16> {
+    let mut b = [0u8; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rasterizer-{}-{}.pak", name, std::process::id()));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn header(count: u32) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&count.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn oversized_count_is_rejected() {
+        let path = temp_file("count", &header(u32::MAX));
+        let err = Archive::open(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn entry_past_the_end_is_rejected() {
+        let mut data = header(1);
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.push(b'a');
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(&content_hash(b"a").to_le_bytes());
+        data.push(b'a');
+
+        let path = temp_file("offset", &data);
+        let err = Archive::open(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
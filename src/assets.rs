@@ -0,0 +1,286 @@
+use std::{env, fmt, fs, io, path::{Component, Path, PathBuf}, sync::Arc};
+
+use crate::{archive::{self, Archive}, config};
+#[cfg(feature = "shader-compiler")]
+use crate::shader;
+
+// Directory searched before the built in locations
+pub const RES_ENV: &str = "RASTERIZER_RES";
+
+// Picked up from the same places as res, after every loose directory so unpacked files override it
+pub const DEFAULT_ARCHIVE: &str = "res.pak";
+
+// Compiled into the binary so a lone executable still renders something, searched after everything on disk. Shaders come
+// from build.rs so they always match their source.
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("shaders/bin/mesh.vert.spv", include_bytes!(concat!(env!("OUT_DIR"), "/shaders/mesh.vert.spv"))),
+    ("shaders/bin/mesh.frag.spv", include_bytes!(concat!(env!("OUT_DIR"), "/shaders/mesh.frag.spv"))),
+    ("shaders/bin/gui.vert.spv", include_bytes!(concat!(env!("OUT_DIR"), "/shaders/gui.vert.spv"))),
+    ("shaders/bin/gui.frag.spv", include_bytes!(concat!(env!("OUT_DIR"), "/shaders/gui.frag.spv"))),
+    ("meshes/asdf.obj", include_bytes!("../res/meshes/asdf.obj")),
+];
+
+// How many directories above the executable to look for res, enough to reach the crate root from target/<profile>
+const EXE_SEARCH_DEPTH: usize = 3;
+
+// Somewhere assets can be found, addressed by logical paths like "meshes/asdf.obj"
+pub trait Mount: Send + Sync {
+    // The file on disk backing a logical path, for mounts that have one
+    fn path(&self, logical: &str) -> Option<PathBuf>;
+    fn read(&self, logical: &str) -> Option<Vec<u8>>;
+    // Shown in errors listing the searched locations
+    fn describe(&self) -> String;
+}
+
+pub struct DirMount {
+    root: PathBuf,
+}
+
+pub struct EmbeddedMount;
+
+// Ordered search list, earlier mounts win. Clones share the mounts.
+#[derive(Clone)]
+pub struct Assets {
+    mounts: Vec<Arc<dyn Mount>>,
+}
+
+pub struct AssetError {
+    pub logical: String,
+    pub searched: Vec<String>,
+}
+
+impl DirMount {
+    pub fn new<P: AsRef<Path>>(root: P) -> DirMount {
+        DirMount {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Mount for DirMount {
+    fn path(&self, logical: &str) -> Option<PathBuf> {
+        // Logical paths stay inside the root, joining an absolute path would replace it entirely
+        if !Path::new(logical).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            return None;
+        }
+
+        let path = self.root.join(logical);
+        path.is_file().then_some(path)
+    }
+
+    fn read(&self, logical: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(logical)?).ok()
+    }
+
+    fn describe(&self) -> String {
+        self.root.display().to_string()
+    }
+}
+
+impl Mount for EmbeddedMount {
+    fn path(&self, _logical: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn read(&self, logical: &str) -> Option<Vec<u8>> {
+        EMBEDDED.iter().find(|(path, _)| *path == logical).map(|(_, data)| data.to_vec())
+    }
+
+    fn describe(&self) -> String {
+        "embedded in the executable".to_string()
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Asset \"{}\" not found, searched:", self.logical)?;
+        if self.searched.is_empty() {
+            write!(f, " nothing, no locations are mounted")?;
+        }
+        for location in &self.searched {
+            write!(f, "\n  {}", location)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets {
+            mounts: Vec::new(),
+        }
+    }
+
+    // $RASTERIZER_RES, then res next to or above the executable, then res in the working directory, then res.pak in any of
+    // those places, then the copies embedded in the executable
+    pub fn with_default_search() -> Assets {
+        let mut assets = Assets::new();
+        let mut bases = Vec::<PathBuf>::new();
+        let mut dirs = Vec::<PathBuf>::new();
+
+        if let Some(dir) = env::var_os(RES_ENV) {
+            dirs.push(PathBuf::from(dir));
+        }
+        if let Some(exe_dir) = env::current_exe().ok().and_then(|e| e.parent().map(|p| p.to_path_buf())) {
+            bases.extend(exe_dir.ancestors().take(EXE_SEARCH_DEPTH + 1).map(|d| d.to_path_buf()));
+        }
+        bases.push(PathBuf::from("."));
+        dirs.extend(bases.iter().map(|b| b.join("res")).filter(|d| d.is_dir()));
+
+        // The same directory can turn up twice, e.g. when running from the crate root
+        let mut seen = Vec::<PathBuf>::new();
+        let mut first_visit = |path: &Path| {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            let first = !seen.contains(&canonical);
+            seen.push(canonical.clone());
+
+            first.then_some(canonical)
+        };
+
+        for dir in dirs {
+            if let Some(dir) = first_visit(&dir) {
+                assets.mount(DirMount::new(dir));
+            }
+        }
+        for archive in bases.iter().map(|b| b.join(DEFAULT_ARCHIVE)).filter(|a| a.is_file()) {
+            if let Some(archive) = first_visit(&archive) {
+                if let Err(e) = assets.mount_archive(&archive) {
+                    println!("Error: Couldn't mount archive \"{}\": {}", archive.display(), e);
+                }
+            }
+        }
+        assets.mount(EmbeddedMount);
+
+        assets
+    }
+
+    // Searched after everything mounted so far
+    pub fn mount<M: Mount + 'static>(&mut self, mount: M) {
+        self.mounts.push(Arc::new(mount));
+    }
+
+    pub fn mount_archive<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.mount(Archive::open(path)?);
+        Ok(())
+    }
+
+    // A file on disk for the logical path, only mounts backed by real files are considered
+    pub fn resolve(&self, logical: &str) -> Result<PathBuf, AssetError> {
+        self.mounts.iter().find_map(|m| m.path(logical)).ok_or_else(|| self.error(logical))
+    }
+
+    // Like resolve, but assets that only exist in an archive are extracted to the cache first. For APIs that only take
+    // paths, like vrg's shader loading.
+    pub fn file(&self, logical: &str) -> Result<PathBuf, AssetError> {
+        for mount in &self.mounts {
+            if let Some(path) = mount.path(logical) {
+                return Ok(path);
+            }
+
+            if let Some(data) = mount.read(logical) {
+                match cache(logical, &data) {
+                    Ok(path) => return Ok(path),
+                    Err(e) => println!("Error: Couldn't extract \"{}\" to the cache: {}", logical, e),
+                }
+            }
+        }
+
+        Err(self.error(logical))
+    }
+
+    // SPIR-V for a shader named like "mesh.vert". With the shader-compiler feature, source found on disk is compiled on the
+    // spot so edits show up without a rebuild, otherwise, or if it doesn't compile, the precompiled binary is used.
+    pub fn shader(&self, name: &str) -> Result<PathBuf, AssetError> {
+        #[cfg(feature = "shader-compiler")]
+        if let Ok(src) = self.resolve(&format!("shaders/src/{}", name)) {
+            match shader::compile(&src) {
+                Ok(compiled) => {
+                    for warning in &compiled.warnings {
+                        println!("Warning: {}", warning);
+                    }
+
+                    match cache(&shader_bin(name), &compiled.spirv) {
+                        Ok(path) => return Ok(path),
+                        Err(e) => println!("Error: Couldn't write compiled shader \"{}\" to the cache: {}", name, e),
+                    }
+                },
+                Err(errors) => {
+                    println!("Error: Couldn't compile shader \"{}\", falling back to the precompiled one:", name);
+                    for error in &errors {
+                        println!("  {}", error);
+                    }
+                },
+            }
+        }
+
+        self.file(&shader_bin(name))
+    }
+
+    pub fn read(&self, logical: &str) -> Result<Vec<u8>, AssetError> {
+        self.mounts.iter().find_map(|m| m.read(logical)).ok_or_else(|| self.error(logical))
+    }
+
+    // Shaders are required, so a missing one is fatal
+    pub fn shader_path(&self, name: &str) -> String {
+        let path = self.shader(name).unwrap_or_else(|e| panic!("Error: {}", e));
+        path.to_string_lossy().to_string()
+    }
+
+    fn error(&self, logical: &str) -> AssetError {
+        AssetError {
+            logical: logical.to_string(),
+            searched: self.mounts.iter().map(|m| m.describe()).collect(),
+        }
+    }
+}
+
+// Compiled SPIR-V for a shader named like "mesh.vert"
+fn shader_bin(name: &str) -> String {
+    format!("shaders/bin/{}.spv", name)
+}
+
+// Writes data to the cache, named by content so a changed asset never picks up an old copy
+fn cache(logical: &str, data: &[u8]) -> io::Result<PathBuf> {
+    let name = Path::new(logical).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let path = config::config_dir().join("cache").join(format!("{:016x}-{}", archive::content_hash(data), name));
+
+    if !path.is_file() {
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, data)?;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_mount_stays_inside_its_root() {
+        let root = env::temp_dir().join(format!("rasterizer-assets-{}", std::process::id()));
+        fs::create_dir_all(root.join("meshes")).unwrap();
+        fs::write(root.join("meshes/a.obj"), "").unwrap();
+        let outside = root.parent().unwrap().join(format!("rasterizer-outside-{}", std::process::id()));
+        fs::write(&outside, "").unwrap();
+
+        let mount = DirMount::new(&root);
+        let name = outside.file_name().unwrap().to_string_lossy().to_string();
+
+        assert!(mount.path("meshes/a.obj").is_some());
+        assert!(mount.path("./meshes/a.obj").is_some());
+        assert!(mount.path("meshes/../meshes/a.obj").is_none());
+        assert!(mount.path(&format!("../{}", name)).is_none());
+        assert!(mount.path(&outside.to_string_lossy()).is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_file(&outside).unwrap();
+    }
+}
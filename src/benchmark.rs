@@ -0,0 +1,188 @@
+use std::{collections::{BTreeMap, VecDeque}, fs::{self, File}, io::{self, BufWriter, Write}, path::Path};
+
+use vrg::math::vec::Vec3;
+
+use crate::{camera::look_at_pose, objects::transform::Transform, profiler::Stats};
+
+// Frames rendered before recording starts, so pipeline creation and first uploads don't skew the results
+const WARMUP: usize = 60;
+
+// Closed loop around and through the instance grid
+const PATH: [[f32; 3]; 8] = [
+    [-10.0, 19.0, -10.0],
+    [19.0, 30.0, -20.0],
+    [48.0, 19.0, -10.0],
+    [58.0, 10.0, 19.0],
+    [48.0, 19.0, 48.0],
+    [19.0, 19.0, 19.0],
+    [-10.0, 30.0, 48.0],
+    [-20.0, 10.0, 19.0],
+];
+
+// Maximum allowed summary values per column, e.g.
+//
+// [frame_ms]
+// p95 = 16.6
+// p99 = 20.0
+//
+// Columns that never show up are ignored, since which sections run can depend on the scene
+pub type Thresholds = BTreeMap<String, BTreeMap<String, f32>>;
+
+pub struct Benchmark {
+    pub frames: usize,
+    frame: usize,
+
+    output: String,
+    thresholds: Thresholds,
+
+    // Fixed on the first recorded frame, anything that shows up later is ignored
+    columns: Vec<String>,
+    rows: Vec<Vec<f32>>,
+}
+
+impl Benchmark {
+    pub fn new(frames: usize, output: &str, thresholds: Thresholds) -> Benchmark {
+        Benchmark {
+            frames,
+            frame: 0,
+
+            output: output.to_string(),
+            thresholds,
+
+            columns: Vec::new(),
+            rows: Vec::with_capacity(frames),
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.frame >= WARMUP + self.frames
+    }
+
+    // Camera pose for the current frame, looking along the path
+    pub fn pose(&self) -> Transform {
+        let t = self.frame.saturating_sub(WARMUP) as f32 / self.frames.max(1) as f32;
+
+        look_at_pose(path_point(t), path_point(t + 0.01))
+    }
+
+    // Call once per frame with the timings measured for it
+    pub fn record(&mut self, samples: Vec<(String, f32)>) {
+        self.frame += 1;
+        if self.frame <= WARMUP {
+            return;
+        }
+
+        if self.columns.is_empty() {
+            self.columns = samples.iter().map(|(name, _)| name.clone()).collect();
+        }
+
+        let samples: BTreeMap<String, f32> = samples.into_iter().collect();
+        self.rows.push(self.columns.iter().map(|c| samples.get(c).copied().unwrap_or(0.0)).collect());
+    }
+
+    // Writes the per frame CSV and the summary next to it, returning false if any threshold was exceeded
+    // or the results couldn't be written
+    pub fn finish(&self) -> bool {
+        match self.write_results() {
+            Ok(passed) => passed,
+            Err(e) => {
+                println!("Error: Couldn't write benchmark results to \"{}\": {}", self.output, e);
+                false
+            },
+        }
+    }
+
+    fn write_results(&self) -> io::Result<bool> {
+        let mut csv = BufWriter::new(File::create(&self.output)?);
+
+        writeln!(csv, "frame,{}", self.columns.join(","))?;
+        for (i, row) in self.rows.iter().enumerate() {
+            let row: Vec<String> = row.iter().map(|v| format!("{:.4}", v)).collect();
+            writeln!(csv, "{},{}", i, row.join(","))?;
+        }
+        csv.flush()?;
+
+        let summary_path = Path::new(&self.output).with_extension("summary.csv");
+        let mut summary = BufWriter::new(File::create(&summary_path)?);
+        let mut passed = true;
+
+        writeln!(summary, "column,mean,median,p95,p99")?;
+        println!("Benchmark: {} frames", self.rows.len());
+        for (i, column) in self.columns.iter().enumerate() {
+            let history: VecDeque<f32> = self.rows.iter().map(|r| r[i]).collect();
+            let stats = match Stats::from_history(&history) {
+                Some(stats) => stats,
+                None => continue,
+            };
+
+            writeln!(summary, "{},{:.4},{:.4},{:.4},{:.4}", column, stats.avg, stats.p50, stats.p95, stats.p99)?;
+            println!("  {}: mean {:.3} ms, median {:.3} ms, p95 {:.3} ms, p99 {:.3} ms", column, stats.avg, stats.p50, stats.p95, stats.p99);
+
+            for (stat, max) in self.thresholds.get(column).into_iter().flatten() {
+                // Names are checked when the thresholds are loaded
+                let value = match stat.as_str() {
+                    "mean" => stats.avg,
+                    "median" => stats.p50,
+                    "p95" => stats.p95,
+                    _ => stats.p99,
+                };
+
+                if value > *max {
+                    println!("  Threshold exceeded: {} {} is {:.3}, limit {:.3}", column, stat, value, max);
+                    passed = false;
+                }
+            }
+        }
+
+        summary.flush()?;
+
+        Ok(passed)
+    }
+}
+
+// Anything unexpected is an error rather than ignored, since a typo would otherwise silently pass everything
+pub fn load_thresholds(path: &str) -> Result<Thresholds, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("Couldn't read benchmark thresholds \"{}\": {}", path, e))?;
+    let thresholds: Thresholds = toml::from_str(&raw).map_err(|e| format!("Couldn't parse benchmark thresholds \"{}\": {}", path, e))?;
+
+    for (column, limits) in &thresholds {
+        for (stat, max) in limits {
+            if !["mean", "median", "p95", "p99"].contains(&stat.as_str()) {
+                return Err(format!("Unknown threshold \"{}\" for \"{}\" in \"{}\", expected mean, median, p95 or p99", stat, column, path));
+            }
+            if !max.is_finite() {
+                return Err(format!("Threshold {} for \"{}\" in \"{}\" isn't a number", stat, column, path));
+            }
+        }
+    }
+
+    Ok(thresholds)
+}
+
+// Catmull-Rom spline through PATH, t wraps around at 1
+fn path_point(t: f32) -> Vec3 {
+    let n = PATH.len();
+    let t = t.rem_euclid(1.0) * n as f32;
+    let i = t as usize;
+    let f = t - i as f32;
+
+    let p = |j: usize| {
+        let [x, y, z] = PATH[(i + j + n - 1) % n];
+        Vec3::new(x, y, z)
+    };
+    let (p0, p1, p2, p3) = (p(0), p(1), p(2), p(3));
+
+    // Uniform Catmull-Rom basis
+    let f2 = f * f;
+    let f3 = f2 * f;
+    let w0 = -0.5 * f3 + f2 - 0.5 * f;
+    let w1 = 1.5 * f3 - 2.5 * f2 + 1.0;
+    let w2 = -1.5 * f3 + 2.0 * f2 + 0.5 * f;
+    let w3 = 0.5 * f3 - 0.5 * f2;
+
+    Vec3::new(
+        p0.x * w0 + p1.x * w1 + p2.x * w2 + p3.x * w3,
+        p0.y * w0 + p1.y * w1 + p2.y * w2 + p3.y * w3,
+        p0.z * w0 + p1.z * w1 + p2.z * w2 + p3.z * w3,
+    )
+}
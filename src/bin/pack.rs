@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::Parser;
+use rasterizer::archive::{self, Archive};
+
+#[path = "../shader.rs"]
+mod shader;
+
+// Shader sources are compiled into the archive alongside themselves, the same logical paths the embedded ones use
+const SHADER_SRC: &str = "shaders/src/";
+const SHADER_BIN: &str = "shaders/bin/";
+
+#[derive(Parser, Debug)]
+#[command(name = "pack", about = "Packs an asset directory into a single archive the rasterizer can mount")]
+struct Args {
+    #[arg(default_value = "res", help = "Directory to pack, paths inside it become the logical asset paths")]
+    root: PathBuf,
+    #[arg(short, long, default_value = "res.pak", help = "Archive to write")]
+    output: PathBuf,
+    #[arg(long, help = "Stores every entry uncompressed")]
+    no_compress: bool,
+    #[arg(long, help = "Lists entries of an existing archive that no longer match root instead of packing")]
+    check: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if !args.root.is_dir() {
+        println!("Error: \"{}\" isn't a directory", args.root.display());
+        process::exit(1);
+    }
+
+    let files = files(&args.root);
+
+    if args.check {
+        let archive = Archive::open(&args.output).unwrap_or_else(|e| {
+            println!("Error: Couldn't open archive \"{}\": {}", args.output.display(), e);
+            process::exit(1);
+        });
+
+        let stale = archive.stale_entries(&files);
+        for entry in &stale {
+            println!("stale: {}", entry.path);
+        }
+        println!("{} of {} entries stale", stale.len(), archive.entries().len());
+
+        process::exit(if stale.is_empty() { 0 } else { 1 });
+    }
+
+    let entries = Archive::build(&files, &args.output, !args.no_compress).unwrap_or_else(|e| {
+        println!("Error: Couldn't build archive \"{}\": {}", args.output.display(), e);
+        process::exit(1);
+    });
+
+    let size: u64 = entries.iter().map(|e| e.size).sum();
+    for entry in &entries {
+        println!("{:>10} {}{}", entry.size, entry.path, if entry.compressed { " (compressed)" } else { "" });
+    }
+    println!("Packed {} files, {} bytes, into \"{}\"", entries.len(), size, args.output.display());
+}
+
+// Everything under root plus the compiled shaders, exits if anything can't be read or doesn't compile
+fn files(root: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut files = archive::collect(root).unwrap_or_else(|e| {
+        println!("Error: Couldn't read \"{}\": {}", root.display(), e);
+        process::exit(1);
+    });
+
+    // Only top level sources are stages, anything nested is an include
+    let sources: Vec<String> = files.keys().filter(|path| {
+        path.strip_prefix(SHADER_SRC).is_some_and(|name| !name.contains('/') && shader::kind(Path::new(name)).is_some())
+    }).cloned().collect();
+
+    let mut failed = false;
+    for source in sources {
+        // Compiled from disk rather than from memory so includes resolve
+        match shader::compile(&root.join(&source)) {
+            Ok(compiled) => {
+                for warning in &compiled.warnings {
+                    println!("Warning: {}", warning);
+                }
+
+                files.insert(format!("{}{}.spv", SHADER_BIN, &source[SHADER_SRC.len()..]), compiled.spirv);
+            },
+            Err(errors) => {
+                println!("Error: Couldn't compile shader \"{}\":", source);
+                for error in &errors {
+                    println!("  {}", error);
+                }
+                failed = true;
+            },
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+
+    files
+}
@@ -0,0 +1,82 @@
+use vrg::math::vec::Vec3;
+
+use crate::{camera::{rotate_wish, Camera, CameraInput}, objects::transform::{Quat, Transform, MAX_PITCH}};
+
+pub struct FirstPersonCamera {
+    pub eye_height: f32,
+    pub ground: f32,
+    pub gravity: f32,
+    pub jump_speed: f32,
+    // Units per second
+    pub walk_speed: f32,
+    pub run_speed: f32,
+
+    // Position of the feet
+    pos: Vec3,
+    fall: f32,
+    pitch: f32,
+    yaw: f32,
+}
+
+impl FirstPersonCamera {
+    pub fn new(pos: Vec3) -> FirstPersonCamera {
+        FirstPersonCamera {
+            eye_height: 1.7,
+            ground: 0.0,
+            gravity: 9.81,
+            jump_speed: 4.5,
+            walk_speed: 4.0,
+            run_speed: 9.0,
+
+            pos,
+            fall: 0.0,
+            pitch: 0.0,
+            yaw: 0.0,
+        }
+    }
+
+    fn grounded(&self) -> bool {
+        self.pos.y <= self.ground
+    }
+}
+
+impl Camera for FirstPersonCamera {
+    fn update(&mut self, input: &CameraInput, dt: f32) {
+        self.pitch = (self.pitch - input.look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw += input.look.x;
+
+        let len = (input.wish.x * input.wish.x + input.wish.z * input.wish.z).sqrt();
+        if len > 0.0 {
+            let speed = if input.boost { self.run_speed } else { self.walk_speed } / len;
+            let (dx, dz) = rotate_wish(input.wish.x, input.wish.z, self.yaw);
+            self.pos.x += dx * speed * dt;
+            self.pos.z += dz * speed * dt;
+        }
+
+        if self.grounded() && input.wish.y > 0.0 {
+            self.fall = self.jump_speed;
+        }
+
+        self.fall -= self.gravity * dt;
+        self.pos.y += self.fall * dt;
+
+        if self.grounded() {
+            self.pos.y = self.ground;
+            self.fall = 0.0;
+        }
+    }
+
+    fn pose(&self) -> Transform {
+        Transform::new(Vec3::new(self.pos.x, self.pos.y + self.eye_height, self.pos.z), Quat::from_euler(self.pitch, self.yaw, 0.0))
+    }
+
+    fn speed(&self) -> Option<f32> {
+        Some(self.walk_speed)
+    }
+
+    // Running keeps the same ratio to walking
+    fn set_speed(&mut self, speed: f32) {
+        self.run_speed *= speed / self.walk_speed;
+        self.walk_speed = speed;
+    }
+}
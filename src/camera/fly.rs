@@ -0,0 +1,103 @@
+use vrg::math::vec::Vec3;
+
+use crate::{camera::{rotate_wish, Camera, CameraInput}, objects::transform::{Quat, Transform, MAX_PITCH}};
+
+const MIN_SPEED: f32 = 0.1;
+const MAX_SPEED: f32 = 1000.0;
+
+pub struct FlyCamera {
+    // Units per second
+    pub speed: f32,
+    // Units per second squared
+    pub accel: f32,
+    // Fraction of velocity lost per second when no key is held (exponential)
+    pub damping: f32,
+    // Speed multiplier while the boost key is held
+    pub boost: f32,
+    // Speed multiplier per wheel notch
+    pub wheel_step: f32,
+    // Radians per second while a roll key is held
+    pub roll_speed: f32,
+    pub allow_roll: bool,
+
+    pos: Vec3,
+    vel: Vec3,
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+}
+
+impl FlyCamera {
+    pub fn new(pos: Vec3) -> FlyCamera {
+        FlyCamera {
+            speed: 12.0,
+            accel: 60.0,
+            damping: 10.0,
+            boost: 4.0,
+            wheel_step: 1.1,
+            roll_speed: 1.5,
+            allow_roll: true,
+
+            pos,
+            vel: Vec3::new(0.0, 0.0, 0.0),
+            pitch: 0.0,
+            yaw: 0.0,
+            roll: 0.0,
+        }
+    }
+
+    // Accelerates towards a held target, or decays towards rest when released
+    fn approach(v: f32, target: f32, max_dv: f32, decay: f32) -> f32 {
+        if target == 0.0 {
+            return v * decay;
+        }
+
+        v + (target - v).clamp(-max_dv, max_dv)
+    }
+}
+
+impl Camera for FlyCamera {
+    fn update(&mut self, input: &CameraInput, dt: f32) {
+        self.pitch = (self.pitch - input.look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw += input.look.x;
+
+        if self.allow_roll {
+            self.roll += input.roll * self.roll_speed * dt;
+        } else {
+            self.roll = 0.0;
+        }
+
+        if input.wheel != 0.0 {
+            self.speed = (self.speed * self.wheel_step.powf(input.wheel)).clamp(MIN_SPEED, MAX_SPEED);
+        }
+
+        let wish = input.wish;
+        let len = (wish.x * wish.x + wish.y * wish.y + wish.z * wish.z).sqrt();
+        let top_speed = if input.boost { self.speed * self.boost } else { self.speed };
+        let scale = if len > 0.0 { top_speed / len } else { 0.0 };
+
+        let max_dv = self.accel * dt;
+        let decay = (-self.damping * dt).exp();
+
+        self.vel.x = Self::approach(self.vel.x, wish.x * scale, max_dv, decay);
+        self.vel.y = Self::approach(self.vel.y, wish.y * scale, max_dv, decay);
+        self.vel.z = Self::approach(self.vel.z, wish.z * scale, max_dv, decay);
+
+        let (dx, dz) = rotate_wish(self.vel.x, self.vel.z, self.yaw);
+        self.pos.x += dx * dt;
+        self.pos.z += dz * dt;
+        self.pos.y += self.vel.y * dt;
+    }
+
+    fn pose(&self) -> Transform {
+        Transform::new(self.pos, Quat::from_euler(self.pitch, self.yaw, self.roll))
+    }
+
+    fn speed(&self) -> Option<f32> {
+        Some(self.speed)
+    }
+
+    fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+}
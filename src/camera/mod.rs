@@ -0,0 +1,76 @@
+pub mod fly;
+pub mod orbit;
+pub mod first_person;
+pub mod turntable;
+pub mod projection;
+
+use serde::{Deserialize, Serialize};
+use vrg::math::vec::{Vec2, Vec3};
+
+use crate::objects::transform::{Quat, Transform, MAX_PITCH};
+
+// Input gathered by the controller for a single fixed step. Look and wheel are only non-zero on
+// the first step of a frame, so cameras can apply them without scaling by dt.
+pub struct CameraInput {
+    // Local wish direction: +x right, +y up, -z forward
+    pub wish: Vec3,
+    pub look: Vec2,
+    pub wheel: f32,
+    // -1 rolls left, 1 rolls right
+    pub roll: f32,
+    pub boost: bool,
+}
+
+pub trait Camera {
+    fn update(&mut self, input: &CameraInput, dt: f32);
+    fn pose(&self) -> Transform;
+
+    // Movement speed in units per second, for cameras the user moves around
+    fn speed(&self) -> Option<f32> {
+        None
+    }
+    fn set_speed(&mut self, _speed: f32) {}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CameraMode {
+    Fly,
+    Orbit,
+    FirstPerson,
+    Turntable,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 4] = [CameraMode::Fly, CameraMode::Orbit, CameraMode::FirstPerson, CameraMode::Turntable];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Fly => "Fly",
+            CameraMode::Orbit => "Orbit",
+            CameraMode::FirstPerson => "First person",
+            CameraMode::Turntable => "Turntable",
+        }
+    }
+}
+
+// Rotates a local x/z wish direction around the y axis by yaw
+pub fn rotate_wish(x: f32, z: f32, yaw: f32) -> (f32, f32) {
+    (x * yaw.cos() - z * yaw.sin(), -(x * yaw.sin() + z * yaw.cos()))
+}
+
+// Pose of a camera looking at target from distance away
+pub fn orbit_pose(target: Vec3, distance: f32, pitch: f32, yaw: f32) -> Transform {
+    let rot = Quat::from_euler(pitch, yaw, 0.0);
+    let dir = rot.rotate(Vec3::new(0.0, 0.0, 1.0));
+
+    Transform::new(Vec3::new(target.x - dir.x * distance, target.y - dir.y * distance, target.z - dir.z * distance), rot)
+}
+
+// Pose at pos looking towards target
+pub fn look_at_pose(pos: Vec3, target: Vec3) -> Transform {
+    let dir = (target - pos).normalize();
+    let pitch = dir.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+    let yaw = dir.x.atan2(dir.z);
+
+    Transform::new(pos, Quat::from_euler(pitch, yaw, 0.0))
+}
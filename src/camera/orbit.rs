@@ -0,0 +1,53 @@
+use vrg::math::vec::Vec3;
+
+use crate::{camera::{orbit_pose, rotate_wish, Camera, CameraInput}, objects::transform::{Transform, MAX_PITCH}};
+
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 500.0;
+
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    // Target movement in units per second, scaled by distance so panning feels the same zoomed in or out
+    pub pan_speed: f32,
+    // Distance multiplier per wheel notch
+    pub zoom_step: f32,
+
+    pitch: f32,
+    yaw: f32,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vec3, distance: f32) -> OrbitCamera {
+        OrbitCamera {
+            target,
+            distance,
+            pan_speed: 0.5,
+            zoom_step: 0.9,
+
+            pitch: -0.4,
+            yaw: 0.0,
+        }
+    }
+}
+
+impl Camera for OrbitCamera {
+    fn update(&mut self, input: &CameraInput, dt: f32) {
+        self.pitch = (self.pitch - input.look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw += input.look.x;
+
+        if input.wheel != 0.0 {
+            self.distance = (self.distance * self.zoom_step.powf(input.wheel)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        }
+
+        let speed = self.pan_speed * self.distance * dt;
+        let (dx, dz) = rotate_wish(input.wish.x, input.wish.z, self.yaw);
+        self.target.x += dx * speed;
+        self.target.z += dz * speed;
+        self.target.y += input.wish.y * speed;
+    }
+
+    fn pose(&self) -> Transform {
+        orbit_pose(self.target, self.distance, self.pitch, self.yaw)
+    }
+}
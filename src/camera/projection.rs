@@ -0,0 +1,73 @@
+use std::f32::consts::PI;
+
+use vrg::math::mat::Mat4;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ProjectionMode {
+    Perspective,
+    Orthographic,
+}
+
+pub struct Projection {
+    pub mode: ProjectionMode,
+
+    // Vertical field of view in radians
+    pub fov: f32,
+    // Width over height of the render target
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+    // Visible height in world units for orthographic mode
+    pub ortho_height: f32,
+}
+
+impl ProjectionMode {
+    pub const ALL: [ProjectionMode; 2] = [ProjectionMode::Perspective, ProjectionMode::Orthographic];
+}
+
+impl Projection {
+    pub fn new(w: u32, h: u32) -> Projection {
+        let mut projection = Projection {
+            mode: ProjectionMode::Perspective,
+
+            fov: PI / 2.0,
+            aspect: 1.0,
+            near: 0.0005,
+            far: 100.0,
+            ortho_height: 10.0,
+        };
+
+        projection.set_target_size(w, h);
+
+        projection
+    }
+
+    pub fn set_target_size(&mut self, w: u32, h: u32) {
+        // Keep the last aspect while minimized rather than dividing by zero
+        if w > 0 && h > 0 {
+            self.aspect = w as f32 / h as f32;
+        }
+    }
+
+    // Matrices here follow Mat4::perspective, which is stored transposed relative to the shaders
+    // (see the transpose in App::update), so mat.r.c is row r, column c of the usual matrix
+    pub fn mat(&self) -> Mat4 {
+        let mut mat = Mat4::perspective(self.aspect, self.fov, self.near, self.far);
+
+        match self.mode {
+            ProjectionMode::Perspective => {},
+            ProjectionMode::Orthographic => {
+                // Keeps the same y direction as the perspective matrix
+                let y_sign = mat.y.y.signum();
+
+                mat = Mat4::identity();
+                mat.x.x = 2.0 / (self.ortho_height * self.aspect);
+                mat.y.y = y_sign * 2.0 / self.ortho_height;
+                mat.z.z = 1.0 / (self.far - self.near);
+                mat.z.w = -self.near / (self.far - self.near);
+            },
+        }
+
+        mat
+    }
+}
@@ -0,0 +1,46 @@
+use vrg::math::vec::Vec3;
+
+use crate::{camera::{orbit_pose, Camera, CameraInput}, objects::transform::Transform};
+
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 500.0;
+
+pub struct TurntableCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    pub pitch: f32,
+    // Radians per second
+    pub spin_speed: f32,
+    // Distance multiplier per wheel notch
+    pub zoom_step: f32,
+
+    yaw: f32,
+}
+
+impl TurntableCamera {
+    pub fn new(target: Vec3, distance: f32) -> TurntableCamera {
+        TurntableCamera {
+            target,
+            distance,
+            pitch: -0.3,
+            spin_speed: 0.5,
+            zoom_step: 0.9,
+
+            yaw: 0.0,
+        }
+    }
+}
+
+impl Camera for TurntableCamera {
+    fn update(&mut self, input: &CameraInput, dt: f32) {
+        self.yaw += self.spin_speed * dt;
+
+        if input.wheel != 0.0 {
+            self.distance = (self.distance * self.zoom_step.powf(input.wheel)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        }
+    }
+
+    fn pose(&self) -> Transform {
+        orbit_pose(self.target, self.distance, self.pitch, self.yaw)
+    }
+}
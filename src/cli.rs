@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+
+use crate::{benchmark, objects::{layout::InstanceLayout, mesh::Mesh}, replay::Replay};
+
+#[derive(Parser, Debug)]
+#[command(name = "rasterizer", about = "Draws many instances of a mesh with Vulkan")]
+pub struct Options {
+    #[arg(help = "Mesh to draw, as a Wavefront OBJ file. Defaults to the last one drawn.")]
+    pub mesh: Option<PathBuf>,
+
+    #[arg(long, value_name = "PAK", help = "Mounts an asset archive built with the pack tool, can be repeated")]
+    pub archive: Vec<PathBuf>,
+
+    #[arg(long, default_value_t = 8000, help = "Number of mesh instances")]
+    pub instances: usize,
+    #[arg(long, value_enum, default_value_t = InstanceLayout::Grid, help = "How instances are arranged")]
+    pub layout: InstanceLayout,
+    #[arg(long, default_value_t = 2.0, help = "Distance between neighbouring instances")]
+    pub spacing: f32,
+
+    #[arg(long, value_parser = parse_resolution, help = "Window size, e.g. 1920x1080")]
+    pub resolution: Option<(u32, u32)>,
+    #[arg(long, help = "Borderless fullscreen on the current monitor")]
+    pub fullscreen: bool,
+    #[arg(long, help = "Disables the Vulkan validation layers")]
+    pub no_validation: bool,
+
+    #[arg(long, conflicts_with = "replay", help = "Records input to a JSON lines file")]
+    pub record: Option<PathBuf>,
+    #[arg(long, help = "Replays input recorded with --record")]
+    pub replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "FRAMES", conflicts_with = "fullscreen", help = "Flies a fixed camera path for this many frames and writes timings")]
+    pub benchmark: Option<usize>,
+    #[arg(long, default_value = "benchmark.csv", help = "CSV written by --benchmark")]
+    pub benchmark_output: PathBuf,
+    #[arg(long, help = "TOML file of per column limits that fail the benchmark")]
+    pub benchmark_thresholds: Option<PathBuf>,
+}
+
+impl Options {
+    // Prints usage or an error and exits if the arguments are invalid
+    pub fn parse_and_validate() -> Options {
+        let options = Options::parse();
+
+        if let Err(e) = options.validate() {
+            Options::command().error(ErrorKind::ValueValidation, e).exit();
+        }
+
+        options
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let exists = |path: &Path, what: &str| if path.is_file() { Ok(()) } else { Err(format!("{} \"{}\" not found", what, path.display())) };
+
+        if let Some(mesh) = &self.mesh {
+            exists(mesh, "Mesh")?;
+            if mesh.extension().map_or(true, |e| !e.eq_ignore_ascii_case("obj")) {
+                return Err(format!("\"{}\" isn't an OBJ file", mesh.display()));
+            }
+            // Parsed now so a malformed mesh is reported here rather than panicking on the render thread
+            Mesh::from_obj(&mesh.to_string_lossy())?;
+        }
+        if self.instances == 0 {
+            return Err("--instances must be at least 1".to_string());
+        }
+        if self.spacing.is_nan() || self.spacing <= 0.0 {
+            return Err("--spacing must be positive".to_string());
+        }
+        if self.benchmark == Some(0) {
+            return Err("--benchmark must be at least 1 frame".to_string());
+        }
+        for path in &self.archive {
+            exists(path, "Archive")?;
+        }
+        if let Some(path) = &self.replay {
+            Replay::load(&path.to_string_lossy())?;
+        }
+        if let Some(path) = &self.benchmark_thresholds {
+            benchmark::load_thresholds(&path.to_string_lossy())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (w, h) = s.split_once('x').ok_or(format!("expected WIDTHxHEIGHT, got \"{}\"", s))?;
+    let w: u32 = w.trim().parse().map_err(|_| format!("invalid width \"{}\"", w))?;
+    let h: u32 = h.trim().parse().map_err(|_| format!("invalid height \"{}\"", h))?;
+
+    if w == 0 || h == 0 {
+        return Err("width and height must be non-zero".to_string());
+    }
+
+    Ok((w, h))
+}
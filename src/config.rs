@@ -0,0 +1,13 @@
+use std::{fs, path::PathBuf};
+
+// Per user directory for anything the app writes, e.g. ~/.config/rasterizer. Falls back to the
+// working directory if there's no such directory on this platform.
+pub fn config_dir() -> PathBuf {
+    let dir = dirs::config_dir().map(|d| d.join("rasterizer")).unwrap_or_else(|| PathBuf::from("."));
+
+    if let Err(e) = fs::create_dir_all(&dir) {
+        println!("Error: Couldn't create config directory \"{}\": {}", dir.display(), e);
+    }
+
+    dir
+}
@@ -0,0 +1,61 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MouseMode {
+    // Cursor hidden and grabbed, motion drives the camera
+    Captured,
+    // Cursor visible, input goes to the GUI
+    Free,
+    // Captured only while the drag binding is held, returns to Free on release
+    Drag,
+}
+
+pub struct Cursor {
+    mode: MouseMode,
+    focused: bool,
+}
+
+impl Cursor {
+    pub fn new() -> Cursor {
+        Cursor {
+            mode: MouseMode::Captured,
+            focused: true,
+        }
+    }
+
+    pub fn mode(&self) -> MouseMode {
+        self.mode
+    }
+
+    // Whether the cursor should currently be grabbed. The mode is kept while unfocused so it can be
+    // restored when focus comes back.
+    pub fn captured(&self) -> bool {
+        self.focused && self.mode != MouseMode::Free
+    }
+
+    pub fn toggle(&mut self) {
+        self.mode = match self.mode {
+            MouseMode::Captured | MouseMode::Drag => MouseMode::Free,
+            MouseMode::Free => MouseMode::Captured,
+        };
+    }
+
+    pub fn begin_drag(&mut self) {
+        if self.mode == MouseMode::Free {
+            self.mode = MouseMode::Drag;
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        if self.mode == MouseMode::Drag {
+            self.mode = MouseMode::Free;
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+
+        // The release would have gone to another window
+        if !focused {
+            self.end_drag();
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use vrg::math::vec::Vec2;
+use winit::event::{ElementState, ModifiersState, MouseButton, VirtualKeyCode};
+
+// Everything the window thread forwards to the app thread
+pub enum AppEvent {
+    Key { key: VirtualKeyCode, state: ElementState },
+    Modifiers(ModifiersState),
+    MouseButton { button: MouseButton, state: ElementState },
+    // Raw relative motion, used for mouse look
+    MouseMotion(Vec2),
+    // Lines scrolled, positive is away from the user
+    Wheel(f32),
+    // Cursor position in physical pixels from the top left of the window
+    CursorPos(Vec2),
+    Text(char),
+    Focused(bool),
+    // Physical size of the window's client area, 0 x 0 when minimized
+    Resized(u32, u32),
+    // Physical position of the window's top left corner on the desktop
+    Moved(i32, i32),
+    ScaleFactor(f64),
+    FileDropped(PathBuf),
+    Quit,
+}
+
+// Requests from the app thread for things only the window thread can do
+pub enum WindowRequest {
+    SetCursorGrab(bool),
+}
@@ -1,8 +1,11 @@
-use std::{os::raw::c_void, ptr::{null, null_mut}, slice};
+use std::os::raw::c_void;
 
 use ash::vk::{self, Handle};
-use imgui::sys::{ImDrawVert, ImVec2};
+use imgui::{ConfigFlags, DrawCmd, DrawCmdParams, FontConfig, FontGlyphRanges, FontSource, Key, TextureId, Ui};
 use vrg::{buffer::BufferBuilder, descriptors::CreationReference, graphics_pass::{GraphicsPassBuilder, GraphicsPassDrawInfo}, image::ImageBuilder, layer::LayerExecution, math::vec::{Vec2, Vec4}, vertex_buffer::{VertexAttribute, VertexAttributes}, Renderer};
+use winit::event::{ElementState, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::{assets::Assets, config, event::AppEvent, profiler};
 
 #[repr(C)]
 #[repr(align(16))]
@@ -40,131 +43,487 @@ impl Default for ImDrawVertWrapper {
     }
 }
 
+// A TTF/OTF file to load into the atlas. Every font after the first is merged into it, which is
+// how icon fonts are added.
+#[derive(Clone)]
+pub struct GuiFont {
+    // Logical asset path
+    pub path: String,
+    // Size in pixels at a scale of 1
+    pub size: f32,
+    // Pairs of inclusive codepoint ranges terminated by a 0, defaults to latin
+    pub glyph_ranges: Option<&'static [u32]>,
+}
+
+// Panels are built every frame until removed
+pub type Panel = Box<dyn FnMut(&Ui)>;
+
 pub struct Gui {
-    ctx: *mut imgui::sys::ImGuiContext,
+    ctx: imgui::Context,
+    panels: Vec<(String, Panel)>,
+
+    layer_name: String,
+    pass_name: String,
+
+    base_style: imgui::Style,
+    dpi_scale: f32,
+    // Applied on top of the window's DPI scale, changes take effect next frame
+    pub ui_scale: f32,
+    applied_scale: f32,
+    // Scale the atlas was rasterized at
+    atlas_scale: f32,
+
+    pub delta: f32,
+
+    // Totals from the last render
+    pub draw_calls: usize,
+    pub vertex_count: usize,
+    pub index_count: usize,
+    // Size of the gui_font image in bytes
+    pub font_bytes: usize,
 }
 
 impl Gui {
-    pub fn new(renderer: &mut Renderer, layer_name: &str, pass_name: &str) -> Gui {
-        unsafe {
-            let ctx = imgui::sys::igCreateContext(null_mut());
-
-            let (w, h) = renderer.get_target_size();
-
-            let io = imgui::sys::igGetIO();
-            let font_atlas = (*io).Fonts;
-
-            (*io).DisplaySize = ImVec2::new(w as f32, h as f32);
-            (*io).DisplayFramebufferScale = ImVec2::new(1.0, 1.0);
-            (*io).DeltaTime = 0.0001;
-
-            let mut font_atlas_width = 0;
-            let mut font_atlas_height = 0;
-            let mut font_atlas_pixels: *mut u8 = 0 as *mut u8; // TODO: make this safer
-            let mut font_atlas_bytes = 0;
-            imgui::sys::ImFontAtlas_GetTexDataAsRGBA32(font_atlas, &mut font_atlas_pixels, &mut font_atlas_width, &mut font_atlas_height, &mut font_atlas_bytes);
-            let font_atlas_width = font_atlas_width as u32;
-            let font_atlas_height = font_atlas_height as u32;
-
-            let font_buffer = BufferBuilder::new()
-                .size(size_of::<u32>() * (font_atlas_width * font_atlas_height) as usize)
-                .usage(vk::BufferUsageFlags::TRANSFER_SRC)
-                .sharing_mode(vk::SharingMode::EXCLUSIVE)
-                .properties(vk::MemoryPropertyFlags::DEVICE_LOCAL)
-                .build_with_data(&renderer.core, &renderer.device, font_atlas_pixels as *const c_void);
-
-            let font_image_builder = ImageBuilder::new()
-                .width(font_atlas_width)
-                .height(font_atlas_height)
-                .depth(1)
-                .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
-                .format(vk::Format::R8G8B8A8_UNORM)
-                .layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                .data(font_buffer);
-
-            renderer.add_images("gui_font", font_image_builder);
-
-            let gui_creation_refs = vec![CreationReference::Sampler("gui_font".to_string())];
-
-            let gui_pass_builder = GraphicsPassBuilder::<ImDrawVertWrapper, u16>::new()
-                .vertex_shader("./res/shaders/bin/gui.vert.spv")
-                .fragment_shader("./res/shaders/bin/gui.frag.spv")
-                .has_verts()
-                .indexed()
-                .resizable_vertex_buffer()
-                .vertex_push_constant::<GUIPushConstant>()
-                .fragment_descriptors(gui_creation_refs, &renderer.data)
-                .draw_info(GraphicsPassDrawInfo::simple_empty())
-                .targets(renderer.get_images("swapchain_image"));
-
-            renderer.add_layer(layer_name, true, LayerExecution::Main);
-            renderer.add_graphics_pass(layer_name, pass_name, gui_pass_builder);
-            renderer.get_layer_mut(layer_name).set_root_path(pass_name);
-
-            let gui_font_desc_set = renderer.get_layer_mut("base").get_graphics_pass_mut("gui").fragment_descriptors.as_mut().expect("Error with GUI pass creation").sets.first().expect("Error with GUI pass descriptor sets").as_raw();
-            (*font_atlas).TexID = gui_font_desc_set as *mut c_void;
-
-            Gui {
-                ctx,
+    // Bytes per vertex in the gui pass's vertex buffer
+    pub const VERTEX_SIZE: usize = size_of::<ImDrawVertWrapper>();
+
+    // Fonts are rasterized once at the starting DPI and UI scale, so text only softens if either grows later
+    pub fn new(renderer: &mut Renderer, assets: &Assets, layer_name: &str, pass_name: &str, fonts: &[GuiFont], dpi_scale: f32, ui_scale: f32) -> Gui {
+        let mut ctx = imgui::Context::create();
+        // Window and dock layout is saved here by ImGui as it changes
+        ctx.set_ini_filename(Some(config::config_dir().join("imgui.ini")));
+        ctx.io_mut().config_flags |= ConfigFlags::DOCKING_ENABLE;
+
+        let (w, h) = renderer.get_target_size();
+        ctx.io_mut().display_size = [w as f32, h as f32];
+        ctx.io_mut().display_framebuffer_scale = [1.0, 1.0];
+
+        let atlas_scale = (dpi_scale * ui_scale).max(1.0);
+        Self::add_fonts(&mut ctx, assets, fonts, atlas_scale);
+        let (font_image_builder, font_bytes) = Self::build_font_atlas(&mut ctx, renderer);
+        renderer.add_images("gui_font", font_image_builder);
+
+        let gui_creation_refs = vec![CreationReference::Sampler("gui_font".to_string())];
+
+        let gui_pass_builder = GraphicsPassBuilder::<ImDrawVertWrapper, u32>::new()
+            .vertex_shader(&assets.shader_path("gui.vert"))
+            .fragment_shader(&assets.shader_path("gui.frag"))
+            .has_verts()
+            .indexed()
+            .resizable_vertex_buffer()
+            .vertex_push_constant::<GUIPushConstant>()
+            .fragment_descriptors(gui_creation_refs, &renderer.data)
+            .draw_info(GraphicsPassDrawInfo::simple_empty())
+            .targets(renderer.get_images("swapchain_image"));
+
+        renderer.add_layer(layer_name, true, LayerExecution::Main);
+        renderer.add_graphics_pass(layer_name, pass_name, gui_pass_builder);
+        renderer.get_layer_mut(layer_name).set_root_path(pass_name);
+
+        let gui_font_desc_set = renderer.get_layer_mut(layer_name).get_graphics_pass_mut(pass_name).fragment_descriptors.as_mut().expect("Error with GUI pass creation").sets.first().expect("Error with GUI pass descriptor sets").as_raw();
+        ctx.fonts().tex_id = TextureId::new(gui_font_desc_set as usize);
+
+        let base_style = *ctx.style();
+
+        Gui {
+            ctx,
+            panels: Vec::new(),
+
+            layer_name: layer_name.to_string(),
+            pass_name: pass_name.to_string(),
+
+            base_style,
+            dpi_scale,
+            ui_scale,
+            // Makes the first update apply the starting scale
+            applied_scale: 0.0,
+            atlas_scale,
+
+            delta: 1.0 / 60.0,
+
+            draw_calls: 0,
+            vertex_count: 0,
+            index_count: 0,
+            font_bytes,
+        }
+    }
+
+    // Also returns the image's size in bytes
+    fn build_font_atlas(ctx: &mut imgui::Context, renderer: &Renderer) -> (ImageBuilder, usize) {
+        let font_atlas = ctx.fonts().build_rgba32_texture();
+        let font_atlas_width = font_atlas.width;
+        let font_atlas_height = font_atlas.height;
+
+        let font_buffer = BufferBuilder::new()
+            .size(size_of::<u32>() * (font_atlas_width * font_atlas_height) as usize)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .properties(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            .build_with_data(&renderer.core, &renderer.device, font_atlas.data.as_ptr() as *const c_void);
+
+        let image_builder = ImageBuilder::new()
+            .width(font_atlas_width)
+            .height(font_atlas_height)
+            .depth(1)
+            .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+            .format(vk::Format::R8G8B8A8_UNORM)
+            .layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .data(font_buffer);
+
+        (image_builder, size_of::<u32>() * (font_atlas_width * font_atlas_height) as usize)
+    }
+
+    // Every font after the first is merged into it. ImGui's built in font stands in for the first if it
+    // doesn't load, so the merged ones still have something to merge into.
+    fn add_fonts(ctx: &mut imgui::Context, assets: &Assets, fonts: &[GuiFont], atlas_scale: f32) {
+        // Font data has to outlive add_font, which copies it into the atlas
+        let data: Vec<Option<Vec<u8>>> = fonts.iter().map(|f| {
+            assets.read(&f.path).map_err(|e| println!("Error: Couldn't load font: {}", e)).ok()
+        }).collect();
+
+        let mut sources = Vec::new();
+        if data.first().map_or(true, |d| d.is_none()) {
+            sources.push(FontSource::DefaultFontData {
+                config: Some(FontConfig {
+                    size_pixels: 13.0 * atlas_scale,
+                    ..FontConfig::default()
+                }),
+            });
+        }
+
+        for (i, (f, d)) in fonts.iter().zip(&data).enumerate() {
+            if let Some(d) = d {
+                sources.push(FontSource::TtfData {
+                    data: d,
+                    size_pixels: f.size * atlas_scale,
+                    config: Some(FontConfig {
+                        glyph_ranges: f.glyph_ranges.map(FontGlyphRanges::from_slice).unwrap_or_else(FontGlyphRanges::default),
+                        // Keeps merged glyphs monospaced so they line up with the text around them
+                        glyph_min_advance_x: if i > 0 { f.size * atlas_scale } else { 0.0 },
+                        ..FontConfig::default()
+                    }),
+                });
             }
         }
+
+        ctx.fonts().add_font(&sources);
+    }
+
+    // Scale factor of the window's monitor, e.g. 2.0 on a 4K monitor at 200%
+    pub fn set_dpi_scale(&mut self, scale: f32) {
+        self.dpi_scale = scale;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.dpi_scale * self.ui_scale
+    }
+
+    // Replacing the atlas image isn't safe while frames using it are in flight, so scale changes only
+    // resize the style and how big the atlas is drawn
+    fn apply_scale(&mut self) {
+        let scale = self.scale();
+        self.ctx.io_mut().font_global_scale = scale / self.atlas_scale;
+
+        *self.ctx.style_mut() = self.base_style;
+        self.ctx.style_mut().scale_all_sizes(scale);
+
+        self.applied_scale = scale;
+    }
+
+    pub fn add_panel<F: FnMut(&Ui) + 'static>(&mut self, name: &str, f: F) {
+        self.panels.push((name.to_string(), Box::new(f)));
     }
 
-    pub fn update<F: Fn()>(&self, f: F) {
-        
+    pub fn remove_panel(&mut self, name: &str) {
+        self.panels.retain(|(n, _)| n != name);
     }
 
-    pub fn render(&self, renderer: &mut Renderer) {
+    // Builds this frame's UI from the registered panels plus f, which can borrow per frame state
+    pub fn update<F: FnOnce(&Ui)>(&mut self, renderer: &mut Renderer, f: F) {
+        let _scope = profiler::scope("Gui build");
+
+        if self.scale() != self.applied_scale {
+            self.apply_scale();
+        }
+
+        let (w, h) = renderer.get_target_size();
+        let io = self.ctx.io_mut();
+        io.display_size = [w as f32, h as f32];
+        io.display_framebuffer_scale = [1.0, 1.0];
+        // ImGui asserts on a zero delta
+        io.delta_time = self.delta.max(0.00001);
+
+        let ui = self.ctx.new_frame();
+
+        // Lets panels dock around the edges while leaving the middle see-through to the scene. The safe
+        // wrapper doesn't take flags, and without passthru the central node paints over the scene.
         unsafe {
-            imgui::sys::igNewFrame();
-
-            let mut col = [0.0, 0.0, 0.0, 0.0];
-
-            imgui::sys::igBegin("asdf".as_ptr() as *const i8, null_mut(), 0);
-            imgui::sys::igText("asdfasdf".as_ptr() as *const i8);
-            imgui::sys::igColorEdit4("aaaa".as_ptr() as *const i8, col.as_mut_ptr(), 0);
-            imgui::sys::igEnd();
-            
-            imgui::sys::igRender();
-            let mut io = imgui::sys::igGetIO();
-            let (w, h) = renderer.get_target_size();
-            (*io).DisplaySize = ImVec2::new(w as f32, h as f32);
-            (*io).DisplayFramebufferScale = ImVec2::new(1.0, 1.0);
-            (*io).DeltaTime = 0.001;
-
-            let draw_data = imgui::sys::igGetDrawData();
-            let mut verts = Vec::<ImDrawVertWrapper>::with_capacity((*draw_data).TotalVtxCount as usize);
-            let mut indices = Vec::<u16>::with_capacity((*draw_data).TotalIdxCount as usize);
-            let lists_count = (*draw_data).CmdListsCount;
-            for i in 0..lists_count {
-                let cmd_list = **(*draw_data).CmdLists.wrapping_add(i as usize);
-                for vert in slice::from_raw_parts(cmd_list.VtxBuffer.Data as *const ImDrawVert, cmd_list.VtxBuffer.Size as usize) {
-                    verts.push(ImDrawVertWrapper { pos: Vec2::new(vert.pos.x, vert.pos.y), uv: Vec2::new(vert.uv.x, vert.uv.y), col: vert.col })
+            imgui::sys::igDockSpaceOverViewport(imgui::sys::igGetMainViewport(), imgui::sys::ImGuiDockNodeFlags_PassthruCentralNode as i32, std::ptr::null());
+        }
+
+        for (_, panel) in &mut self.panels {
+            panel(ui);
+        }
+
+        f(ui);
+    }
+
+    // Forwards window input to ImGui. Mouse events should only be passed while the cursor is free.
+    pub fn handle_event(&mut self, event: &AppEvent) {
+        let io = self.ctx.io_mut();
+
+        match event {
+            AppEvent::Key { key, state } => {
+                if let Some(key) = imgui_key(*key) {
+                    io.add_key_event(key, *state == ElementState::Pressed);
+                }
+            },
+            AppEvent::Modifiers(m) => {
+                io.add_key_event(Key::ModCtrl, m.contains(ModifiersState::CTRL));
+                io.add_key_event(Key::ModShift, m.contains(ModifiersState::SHIFT));
+                io.add_key_event(Key::ModAlt, m.contains(ModifiersState::ALT));
+                io.add_key_event(Key::ModSuper, m.contains(ModifiersState::LOGO));
+            },
+            AppEvent::Text(c) => {
+                // Control characters arrive as text too, ImGui gets those through key events
+                if !c.is_control() {
+                    io.add_input_character(*c);
+                }
+            },
+            AppEvent::MouseButton { button, state } => {
+                let button = match button {
+                    MouseButton::Left => imgui::MouseButton::Left,
+                    MouseButton::Right => imgui::MouseButton::Right,
+                    MouseButton::Middle => imgui::MouseButton::Middle,
+                    MouseButton::Other(3) => imgui::MouseButton::Extra1,
+                    MouseButton::Other(4) => imgui::MouseButton::Extra2,
+                    MouseButton::Other(_) => return,
+                };
+
+                io.add_mouse_button_event(button, *state == ElementState::Pressed);
+            },
+            AppEvent::CursorPos(p) => io.add_mouse_pos_event([p.x, p.y]),
+            AppEvent::Wheel(d) => io.add_mouse_wheel_event([0.0, *d]),
+            AppEvent::Focused(f) => unsafe {
+                // No safe wrapper for this one
+                imgui::sys::ImGuiIO_AddFocusEvent(imgui::sys::igGetIO(), *f);
+            },
+            _ => {},
+        }
+    }
+
+    // Stops ImGui hovering whatever was last under the cursor once it's captured
+    pub fn clear_mouse(&mut self) {
+        self.ctx.io_mut().add_mouse_pos_event([-f32::MAX, -f32::MAX]);
+    }
+
+    pub fn want_capture_mouse(&self) -> bool {
+        self.ctx.io().want_capture_mouse
+    }
+
+    pub fn want_capture_keyboard(&self) -> bool {
+        self.ctx.io().want_capture_keyboard
+    }
+
+    // Uploads the frame built by update to the gui pass
+    pub fn render(&mut self, renderer: &mut Renderer) {
+        let _scope = profiler::scope("Gui upload");
+
+        let font_texture = self.ctx.fonts().tex_id;
+        let draw_data = self.ctx.render();
+        let display_pos = draw_data.display_pos;
+        let display_size = draw_data.display_size;
+
+        let mut verts = Vec::<ImDrawVertWrapper>::with_capacity(draw_data.total_vtx_count as usize);
+        let mut indices = Vec::<u32>::with_capacity(draw_data.total_idx_count as usize);
+
+        for draw_list in draw_data.draw_lists() {
+            // Lists are concatenated into one buffer, so their indices are rebased onto it here.
+            // Indices are 32 bit so this can't overflow however big the UI gets.
+            let list_vert_base = verts.len() as u32;
+
+            for vert in draw_list.vtx_buffer() {
+                verts.push(ImDrawVertWrapper { pos: Vec2::new(vert.pos[0], vert.pos[1]), uv: Vec2::new(vert.uv[0], vert.uv[1]), col: u32::from_le_bytes(vert.col) })
+            }
+
+            let list_indices = draw_list.idx_buffer();
+
+            // The pass draws everything in one call with the font atlas bound and vrg draw infos have no
+            // scissor, so clip rects are applied to the triangles here instead
+            for cmd in draw_list.commands() {
+                // No callbacks are ever registered
+                let (count, DrawCmdParams { clip_rect, texture_id, vtx_offset, idx_offset }) = match cmd {
+                    DrawCmd::Elements { count, cmd_params } => (count, cmd_params),
+                    _ => continue,
+                };
+
+                // Nothing else could be bound, so other textures would draw with the atlas
+                if texture_id != font_texture {
+                    continue;
                 }
-                for index in slice::from_raw_parts(cmd_list.IdxBuffer.Data, cmd_list.IdxBuffer.Size as usize) {
-                    indices.push(*index);
+
+                let cmd_vert_base = list_vert_base + vtx_offset as u32;
+                for tri in list_indices[idx_offset..idx_offset + count].chunks_exact(3) {
+                    let tri = [cmd_vert_base + tri[0] as u32, cmd_vert_base + tri[1] as u32, cmd_vert_base + tri[2] as u32];
+
+                    match clip_triangle(tri.map(|i| verts[i as usize]), clip_rect) {
+                        Clipped::Inside => indices.extend(tri),
+                        Clipped::Outside => {},
+                        Clipped::Partial(poly) => {
+                            // Clipping leaves a convex polygon, drawn as a fan of new verts
+                            let first = verts.len() as u32;
+                            verts.extend(&poly);
+                            for i in 1..poly.len() as u32 - 1 {
+                                indices.extend([first, first + i, first + i + 1]);
+                            }
+                        },
+                    }
                 }
             }
+        }
 
-            let display_size = (*draw_data).DisplaySize;
-            let scale = Vec2 {
-                x: 2.0 / display_size.x,
-                y: 2.0 / display_size.y,
-            };
-            // TODO: This won't work with multiple viewports
-            let pre_translate = Vec2::zero();
-            let gui_push_constant = GUIPushConstant {
-                scale,
-                pre_translate
-            };
-
-            renderer.get_layer_mut("base").fill_vertex_push_constant("gui", &gui_push_constant);
-
-            if verts.len() > 0 {
-                renderer.update_vertex_buffer("base", "gui", Some(&verts), Some(&indices));
-                renderer.get_layer_mut("base").get_graphics_pass_mut("gui").draw_infos.clear();
-                renderer.get_layer_mut("base").get_graphics_pass_mut("gui").draw_infos.push(GraphicsPassDrawInfo::simple_indexed(verts.len(), indices.len()));
+        // Maps display space onto clip space
+        let scale = Vec2 {
+            x: 2.0 / display_size[0],
+            y: 2.0 / display_size[1],
+        };
+        // TODO: This won't work with multiple viewports
+        let pre_translate = Vec2 {
+            x: -display_pos[0] - display_size[0] / 2.0,
+            y: -display_pos[1] - display_size[1] / 2.0,
+        };
+        let gui_push_constant = GUIPushConstant {
+            scale,
+            pre_translate
+        };
+
+        let layer = renderer.get_layer_mut(&self.layer_name);
+        layer.fill_vertex_push_constant(&self.pass_name, &gui_push_constant);
+        layer.get_graphics_pass_mut(&self.pass_name).draw_infos.clear();
+
+        self.draw_calls = if indices.is_empty() { 0 } else { 1 };
+        self.vertex_count = verts.len();
+        self.index_count = indices.len();
+
+        if !indices.is_empty() {
+            renderer.update_vertex_buffer(&self.layer_name, &self.pass_name, Some(&verts), Some(&indices));
+            renderer.get_layer_mut(&self.layer_name).get_graphics_pass_mut(&self.pass_name).draw_infos.push(GraphicsPassDrawInfo::simple_indexed(verts.len(), indices.len()));
+        }
+    }
+}
+
+enum Clipped {
+    Inside,
+    Outside,
+    Partial(Vec<ImDrawVertWrapper>),
+}
+
+// Clips a triangle to an [x1, y1, x2, y2] rect
+fn clip_triangle(tri: [ImDrawVertWrapper; 3], rect: [f32; 4]) -> Clipped {
+    // Each edge as the axis, the bound and whether the kept side is above it
+    let edges = [(0, rect[0], true), (1, rect[1], true), (0, rect[2], false), (1, rect[3], false)];
+    let dist = |v: &ImDrawVertWrapper, (axis, bound, above): (usize, f32, bool)| {
+        let p = if axis == 0 { v.pos.x } else { v.pos.y };
+        if above { p - bound } else { bound - p }
+    };
+
+    if edges.iter().all(|&e| tri.iter().all(|v| dist(v, e) >= 0.0)) {
+        return Clipped::Inside;
+    }
+    if edges.iter().any(|&e| tri.iter().all(|v| dist(v, e) < 0.0)) {
+        return Clipped::Outside;
+    }
+
+    // Sutherland-Hodgman, one edge at a time
+    let mut poly = tri.to_vec();
+    for e in edges {
+        let input = std::mem::take(&mut poly);
+        for (i, a) in input.iter().enumerate() {
+            let b = &input[(i + 1) % input.len()];
+            let (da, db) = (dist(a, e), dist(b, e));
+
+            if da >= 0.0 {
+                poly.push(*a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                poly.push(lerp_vert(a, b, da / (da - db)));
             }
         }
+
+        if poly.len() < 3 {
+            return Clipped::Outside;
+        }
     }
-}
\ No newline at end of file
+
+    Clipped::Partial(poly)
+}
+
+fn lerp_vert(a: &ImDrawVertWrapper, b: &ImDrawVertWrapper, t: f32) -> ImDrawVertWrapper {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let (col_a, col_b) = (a.col.to_le_bytes(), b.col.to_le_bytes());
+
+    ImDrawVertWrapper {
+        pos: Vec2::new(lerp(a.pos.x, b.pos.x), lerp(a.pos.y, b.pos.y)),
+        uv: Vec2::new(lerp(a.uv.x, b.uv.x), lerp(a.uv.y, b.uv.y)),
+        col: u32::from_le_bytes(std::array::from_fn(|i| lerp(col_a[i] as f32, col_b[i] as f32).round() as u8)),
+    }
+}
+
+fn imgui_key(vk: VirtualKeyCode) -> Option<Key> {
+    let key = match vk {
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Left => Key::LeftArrow,
+        VirtualKeyCode::Right => Key::RightArrow,
+        VirtualKeyCode::Up => Key::UpArrow,
+        VirtualKeyCode::Down => Key::DownArrow,
+        VirtualKeyCode::PageUp => Key::PageUp,
+        VirtualKeyCode::PageDown => Key::PageDown,
+        VirtualKeyCode::Home => Key::Home,
+        VirtualKeyCode::End => Key::End,
+        VirtualKeyCode::Insert => Key::Insert,
+        VirtualKeyCode::Delete => Key::Delete,
+        VirtualKeyCode::Back => Key::Backspace,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::Return => Key::Enter,
+        VirtualKeyCode::Escape => Key::Escape,
+        VirtualKeyCode::LControl => Key::LeftCtrl,
+        VirtualKeyCode::LShift => Key::LeftShift,
+        VirtualKeyCode::LAlt => Key::LeftAlt,
+        VirtualKeyCode::LWin => Key::LeftSuper,
+        VirtualKeyCode::RControl => Key::RightCtrl,
+        VirtualKeyCode::RShift => Key::RightShift,
+        VirtualKeyCode::RAlt => Key::RightAlt,
+        VirtualKeyCode::RWin => Key::RightSuper,
+        VirtualKeyCode::Key0 => Key::Alpha0,
+        VirtualKeyCode::Key1 => Key::Alpha1,
+        VirtualKeyCode::Key2 => Key::Alpha2,
+        VirtualKeyCode::Key3 => Key::Alpha3,
+        VirtualKeyCode::Key4 => Key::Alpha4,
+        VirtualKeyCode::Key5 => Key::Alpha5,
+        VirtualKeyCode::Key6 => Key::Alpha6,
+        VirtualKeyCode::Key7 => Key::Alpha7,
+        VirtualKeyCode::Key8 => Key::Alpha8,
+        VirtualKeyCode::Key9 => Key::Alpha9,
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::C => Key::C,
+        VirtualKeyCode::V => Key::V,
+        VirtualKeyCode::X => Key::X,
+        VirtualKeyCode::Y => Key::Y,
+        VirtualKeyCode::Z => Key::Z,
+        VirtualKeyCode::Apostrophe => Key::Apostrophe,
+        VirtualKeyCode::Comma => Key::Comma,
+        VirtualKeyCode::Minus => Key::Minus,
+        VirtualKeyCode::Period => Key::Period,
+        VirtualKeyCode::Slash => Key::Slash,
+        VirtualKeyCode::Semicolon => Key::Semicolon,
+        VirtualKeyCode::Equals => Key::Equal,
+        VirtualKeyCode::LBracket => Key::LeftBracket,
+        VirtualKeyCode::Backslash => Key::Backslash,
+        VirtualKeyCode::RBracket => Key::RightBracket,
+        VirtualKeyCode::Grave => Key::GraveAccent,
+        VirtualKeyCode::NumpadEnter => Key::KeypadEnter,
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -0,0 +1,347 @@
+use std::{collections::{HashMap, HashSet}, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use vrg::math::vec::Vec2;
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::{config, settings::Settings};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Ascend,
+    Descend,
+    RollLeft,
+    RollRight,
+    Boost,
+    ToggleCursor,
+    // Looks around while held, when the cursor is free
+    DragLook,
+    Quit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Axis {
+    LookX,
+    LookY,
+    Zoom,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum AxisSource {
+    MouseX,
+    MouseY,
+    Wheel,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    pub source: AxisSource,
+    pub scale: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActionBinding {
+    pub action: Action,
+    pub inputs: Vec<Binding>,
+}
+
+// Layout of the bindings file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputConfig {
+    pub invert_y: bool,
+    pub bindings: Vec<ActionBinding>,
+    pub axes: Vec<AxisBinding>,
+}
+
+pub struct Input {
+    pub bindings: HashMap<Action, Vec<Binding>>,
+    pub axes: Vec<AxisBinding>,
+    pub sensitivity: f32,
+    pub invert_y: bool,
+
+    // Set from the GUI, the next key or mouse button replaces this action's bindings
+    pub rebinding: Option<Action>,
+
+    held: HashSet<Binding>,
+    down: HashSet<Action>,
+
+    // Transitions are collected from events so a press and release between two frames still registers
+    pressed: HashSet<Action>,
+    released: HashSet<Action>,
+    frame_pressed: HashSet<Action>,
+    frame_released: HashSet<Action>,
+
+    mouse_delta: Vec2,
+    wheel: f32,
+    frame_mouse_delta: Vec2,
+    frame_wheel: f32,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Ascend,
+        Action::Descend,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::Boost,
+        Action::ToggleCursor,
+        Action::DragLook,
+        Action::Quit,
+    ];
+}
+
+impl Default for InputConfig {
+    fn default() -> InputConfig {
+        let key = |action, key| ActionBinding { action, inputs: vec![Binding::Key(key)] };
+
+        InputConfig {
+            invert_y: false,
+            bindings: vec![
+                key(Action::MoveForward, VirtualKeyCode::W),
+                key(Action::MoveBack, VirtualKeyCode::S),
+                key(Action::MoveLeft, VirtualKeyCode::A),
+                key(Action::MoveRight, VirtualKeyCode::D),
+                key(Action::Ascend, VirtualKeyCode::Space),
+                key(Action::Descend, VirtualKeyCode::LShift),
+                key(Action::RollLeft, VirtualKeyCode::Q),
+                key(Action::RollRight, VirtualKeyCode::E),
+                key(Action::Boost, VirtualKeyCode::LControl),
+                key(Action::ToggleCursor, VirtualKeyCode::Tab),
+                ActionBinding { action: Action::DragLook, inputs: vec![Binding::Mouse(MouseButton::Right)] },
+                key(Action::Quit, VirtualKeyCode::Escape),
+            ],
+            axes: vec![
+                AxisBinding { axis: Axis::LookX, source: AxisSource::MouseX, scale: 1.0 },
+                AxisBinding { axis: Axis::LookY, source: AxisSource::MouseY, scale: 1.0 },
+                AxisBinding { axis: Axis::Zoom, source: AxisSource::Wheel, scale: 1.0 },
+            ],
+        }
+    }
+}
+
+impl Input {
+    pub fn new(config: InputConfig) -> Input {
+        let mut input = Input {
+            bindings: HashMap::new(),
+            axes: config.axes,
+            // Saved in settings.toml rather than with the bindings, App::apply_settings sets it
+            sensitivity: Settings::default().mouse_sensitivity,
+            invert_y: config.invert_y,
+
+            rebinding: None,
+
+            held: HashSet::new(),
+            down: HashSet::new(),
+
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            frame_pressed: HashSet::new(),
+            frame_released: HashSet::new(),
+
+            mouse_delta: Vec2::zero(),
+            wheel: 0.0,
+            frame_mouse_delta: Vec2::zero(),
+            frame_wheel: 0.0,
+        };
+
+        for binding in config.bindings {
+            input.bindings.entry(binding.action).or_default().extend(binding.inputs);
+        }
+
+        input
+    }
+
+    pub fn path() -> PathBuf {
+        config::config_dir().join("input.toml")
+    }
+
+    // Falls back to the default bindings if the file is missing or malformed
+    pub fn load() -> Input {
+        let path = Input::path();
+        let config = match fs::read_to_string(&path) {
+            Ok(raw) => toml::from_str(&raw).unwrap_or_else(|e| {
+                println!("Error: Couldn't parse input bindings \"{}\", using defaults: {}", path.display(), e);
+                InputConfig::default()
+            }),
+            Err(_) => InputConfig::default(),
+        };
+
+        Input::new(config)
+    }
+
+    pub fn save(&self) {
+        let path = Input::path();
+        let raw = toml::to_string_pretty(&self.config()).expect("Error serializing input bindings");
+        if let Err(e) = fs::write(&path, raw) {
+            println!("Error: Couldn't save input bindings to \"{}\": {}", path.display(), e);
+        }
+    }
+
+    pub fn config(&self) -> InputConfig {
+        InputConfig {
+            invert_y: self.invert_y,
+            bindings: Action::ALL.iter().map(|a| ActionBinding { action: *a, inputs: self.bindings.get(a).cloned().unwrap_or_default() }).collect(),
+            axes: self.axes.clone(),
+        }
+    }
+
+    // Returns true if the event was consumed by an in progress rebind. Releases always go through
+    // so keys held when rebinding starts don't get stuck.
+    pub fn handle_binding(&mut self, binding: Binding, state: ElementState) -> bool {
+        if let Some(action) = self.rebinding {
+            if state == ElementState::Pressed {
+                self.bindings.insert(action, vec![binding]);
+                self.rebinding = None;
+                return true;
+            }
+        }
+
+        let before = self.active();
+        match state {
+            ElementState::Pressed => self.held.insert(binding),
+            ElementState::Released => self.held.remove(&binding),
+        };
+        self.collect_transitions(&before);
+
+        false
+    }
+
+    pub fn handle_motion(&mut self, d: Vec2) {
+        self.mouse_delta += d;
+    }
+
+    pub fn handle_wheel(&mut self, d: f32) {
+        self.wheel += d;
+    }
+
+    // Latches the events received since the last frame, call once per frame before reading actions
+    pub fn begin_frame(&mut self) {
+        self.down = self.active();
+        self.frame_pressed = std::mem::take(&mut self.pressed);
+        self.frame_released = std::mem::take(&mut self.released);
+
+        self.frame_mouse_delta = self.mouse_delta;
+        self.frame_wheel = self.wheel;
+        self.mouse_delta = Vec2::zero();
+        self.wheel = 0.0;
+    }
+
+    // Drops held state, e.g. when focus is lost and releases won't arrive
+    pub fn release_all(&mut self) {
+        let before = self.active();
+        self.held.clear();
+        self.collect_transitions(&before);
+    }
+
+    // Actions with at least one of their bindings held
+    fn active(&self) -> HashSet<Action> {
+        self.bindings.iter().filter(|(_, inputs)| inputs.iter().any(|b| self.held.contains(b))).map(|(a, _)| *a).collect()
+    }
+
+    fn collect_transitions(&mut self, before: &HashSet<Action>) {
+        let after = self.active();
+        self.pressed.extend(after.difference(before));
+        self.released.extend(before.difference(&after));
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.frame_pressed.contains(&action)
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        self.frame_released.contains(&action)
+    }
+
+    // Look axes are in radians, zoom is in wheel notches
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes.iter().filter(|a| a.axis == axis).map(|a| {
+            let raw = match a.source {
+                AxisSource::MouseX => self.frame_mouse_delta.x * self.sensitivity,
+                AxisSource::MouseY => self.frame_mouse_delta.y * self.sensitivity * if self.invert_y { -1.0 } else { 1.0 },
+                AxisSource::Wheel => self.frame_wheel,
+            };
+
+            raw * a.scale
+        }).sum()
+    }
+
+    pub fn binding_name(binding: &Binding) -> String {
+        match binding {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(input: &mut Input, key: VirtualKeyCode, state: ElementState) -> bool {
+        input.handle_binding(Binding::Key(key), state)
+    }
+
+    #[test]
+    fn tap_within_one_frame_registers() {
+        let mut input = Input::new(InputConfig::default());
+        key(&mut input, VirtualKeyCode::Escape, ElementState::Pressed);
+        key(&mut input, VirtualKeyCode::Escape, ElementState::Released);
+        input.begin_frame();
+
+        assert!(input.pressed(Action::Quit));
+        assert!(input.released(Action::Quit));
+        assert!(!input.down(Action::Quit));
+
+        input.begin_frame();
+        assert!(!input.pressed(Action::Quit));
+        assert!(!input.released(Action::Quit));
+    }
+
+    #[test]
+    fn key_repeat_is_not_a_press() {
+        let mut input = Input::new(InputConfig::default());
+        key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+        input.begin_frame();
+        key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+        input.begin_frame();
+
+        assert!(input.down(Action::MoveForward));
+        assert!(!input.pressed(Action::MoveForward));
+    }
+
+    #[test]
+    fn release_while_rebinding_is_applied() {
+        let mut input = Input::new(InputConfig::default());
+        key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+        input.begin_frame();
+
+        input.rebinding = Some(Action::Quit);
+        assert!(!key(&mut input, VirtualKeyCode::W, ElementState::Released));
+        assert!(key(&mut input, VirtualKeyCode::F10, ElementState::Pressed));
+        input.begin_frame();
+
+        assert!(!input.down(Action::MoveForward));
+        assert!(input.released(Action::MoveForward));
+        assert_eq!(input.bindings[&Action::Quit], vec![Binding::Key(VirtualKeyCode::F10)]);
+        assert!(!input.pressed(Action::Quit));
+    }
+}
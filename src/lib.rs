@@ -1,4 +1,20 @@
 pub mod app;
+pub mod archive;
+pub mod cli;
+pub mod event;
+pub mod settings;
+mod assets;
+mod benchmark;
+mod camera;
+mod config;
 mod controller;
+mod cursor;
 mod objects;
-mod gui;
\ No newline at end of file
+mod gui;
+mod input;
+mod panels;
+mod profiler;
+mod render_stats;
+mod replay;
+#[cfg(feature = "shader-compiler")]
+mod shader;
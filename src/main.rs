@@ -2,21 +2,17 @@ use std::sync::mpsc;
 use std::thread;
 
 use rasterizer::app;
+use rasterizer::cli::Options;
+use rasterizer::event::{AppEvent, WindowRequest};
+use rasterizer::settings::Settings;
 use vrg::math::vec::Vec2;
 use vrg::util::window;
 use raw_window_handle::{HasRawWindowHandle, HasRawDisplayHandle, RawWindowHandle, RawDisplayHandle};
-use winit::event::{Event, VirtualKeyCode, WindowEvent, DeviceEvent, ElementState};
+use winit::event::{Event, WindowEvent, DeviceEvent, MouseScrollDelta};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::window::{CursorGrabMode, Fullscreen};
 use winit::event_loop::{ControlFlow, EventLoop};
 
-pub struct KeyboardMessage {
-    key: VirtualKeyCode,
-    state: ElementState,
-}
-
-pub struct MouseMessage {
-    delta: Vec2,
-}
-
 pub struct RawWindowDataWrapper {
     window_handle: RawWindowHandle,
     display_handle: RawDisplayHandle,
@@ -27,75 +23,164 @@ unsafe impl Sync for RawWindowDataWrapper {}
 
 fn main() {
     unsafe {
+        // Before the window opens, so bad arguments don't flash one up
+        let options = Options::parse_and_validate();
+        // Benchmarks run with the defaults, including the window size, so runs on different setups compare
+        let settings = if options.benchmark.is_some() { Settings::default() } else { Settings::load() };
+
         let event_loop = EventLoop::new();
         let mut window = window::Window::new(&event_loop);
 
-        let (key_t, key_r) = mpsc::channel::<KeyboardMessage>();
-        let (mouse_t, mouse_r) = mpsc::channel::<MouseMessage>();
+        // The command line takes priority over the size the window was last closed at
+        let (w, h) = options.resolution.unwrap_or((settings.window.width, settings.window.height));
+        window.window.set_inner_size(PhysicalSize::new(w, h));
+        window.res = (w, h);
+        if let (Some(x), Some(y)) = (settings.window.x, settings.window.y) {
+            window.window.set_outer_position(PhysicalPosition::new(x, y));
+        }
+        if options.fullscreen {
+            window.window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+
+        let (event_t, event_r) = mpsc::channel::<AppEvent>();
+        let (request_t, request_r) = mpsc::channel::<WindowRequest>();
 
+        // Later changes arrive as ScaleFactorChanged
+        let scale_factor = window.window.scale_factor();
+
+        let res = window.res;
         let raw_window_data = RawWindowDataWrapper {
             window_handle: window.window.raw_window_handle(),
             display_handle: window.window.raw_display_handle(),
         };
 
-        let app_handle = thread::spawn(move || {
+        let mut app_handle = Some(thread::spawn(move || {
             let raw_window_data_copy = raw_window_data;
-            let mut app = app::App::new(raw_window_data_copy.window_handle, raw_window_data_copy.display_handle, Vec2::new(window.res.0 as f32, window.res.1 as f32));
+            let mut app = app::App::new(raw_window_data_copy.window_handle, raw_window_data_copy.display_handle, Vec2::new(res.0 as f32, res.1 as f32), scale_factor, request_t, &options, settings);
 
-            while true {
-                match key_r.try_recv() {
-                    Ok(msg) => {
-                        app.update_key(msg.key, msg.state);
-                    },
-                    _ => {},
+            if let Some(path) = &options.record {
+                app.start_recording(&path.to_string_lossy());
+            }
+            if let Some(path) = &options.replay {
+                app.start_replay(&path.to_string_lossy());
+            }
+            if let Some(frames) = options.benchmark {
+                let thresholds = options.benchmark_thresholds.as_ref().map(|p| p.to_string_lossy().to_string());
+                app.start_benchmark(frames, &options.benchmark_output.to_string_lossy(), thresholds.as_deref());
+            }
+
+            while app.running {
+                while let Ok(event) = event_r.try_recv() {
+                    app.handle_event(event);
                 }
 
-                let mut mouse_msg = mouse_r.try_recv();
-                while mouse_msg.is_ok() {
-                    app.update_mouse(mouse_msg.unwrap().delta);
-                    mouse_msg = mouse_r.try_recv();
+                if !app.running {
+                    break;
                 }
 
                 app.main_loop();
             }
-        });
+
+            let exit_code = app.exit_code;
+            app.shutdown();
+
+            exit_code
+        }));
 
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Poll;
 
-            match event {
-                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
-                    *control_flow = ControlFlow::Exit;
-                },
-                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => {
-                    if input.virtual_keycode.is_some() {
-                        if input.virtual_keycode.unwrap() == VirtualKeyCode::Escape {
-                            window.window.set_cursor_grab(winit::window::CursorGrabMode::None).unwrap();
-                            window.window.set_cursor_visible(true);
-                        }
+            // The app thread may have already exited, in which case there's nobody left to tell
+            let send = |e: AppEvent| {
+                let _ = event_t.send(e);
+            };
 
-                        let key_message = KeyboardMessage {
-                            key: input.virtual_keycode.unwrap(),
-                            state: input.state,
-                        };
+            let mut quit = false;
 
-                        if key_message.key == VirtualKeyCode::Escape && key_message.state == ElementState::Pressed {
-                            *control_flow = ControlFlow::Exit;
+            while let Ok(request) = request_r.try_recv() {
+                match request {
+                    WindowRequest::SetCursorGrab(grab) => {
+                        if grab {
+                            // Not every platform supports both modes
+                            window.window.set_cursor_grab(CursorGrabMode::Confined)
+                                .or_else(|_| window.window.set_cursor_grab(CursorGrabMode::Locked))
+                                .unwrap_or_else(|e| println!("Error: Couldn't grab cursor: {}", e));
                         } else {
-                            key_t.send(key_message).unwrap();
+                            window.window.set_cursor_grab(CursorGrabMode::None).unwrap();
                         }
+                        window.window.set_cursor_visible(!grab);
+                    },
+                }
+            }
+
+            match event {
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                    quit = true;
+                },
+                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => {
+                    if let Some(key) = input.virtual_keycode {
+                        send(AppEvent::Key { key, state: input.state });
                     }
                 },
+                Event::WindowEvent { event: WindowEvent::ModifiersChanged(m), .. } => {
+                    send(AppEvent::Modifiers(m));
+                },
+                Event::WindowEvent { event: WindowEvent::MouseInput { button, state, .. }, .. } => {
+                    send(AppEvent::MouseButton { button, state });
+                },
                 Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
                     if window.focused {
-                        mouse_t.send(MouseMessage { delta: Vec2::new(delta.0 as f32, delta.1 as f32) }).unwrap();
+                        send(AppEvent::MouseMotion(Vec2::new(delta.0 as f32, delta.1 as f32)));
                     }
                 }
+                Event::WindowEvent { event: WindowEvent::CursorMoved { position, .. }, .. } => {
+                    send(AppEvent::CursorPos(Vec2::new(position.x as f32, position.y as f32)));
+                },
+                Event::WindowEvent { event: WindowEvent::MouseWheel { delta, .. }, .. } => {
+                    let d = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(p) => p.y as f32 / 120.0,
+                    };
+
+                    send(AppEvent::Wheel(d));
+                },
+                Event::WindowEvent { event: WindowEvent::ReceivedCharacter(c), .. } => {
+                    send(AppEvent::Text(c));
+                },
+                Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                    send(AppEvent::Resized(size.width, size.height));
+                },
+                Event::WindowEvent { event: WindowEvent::Moved(position), .. } => {
+                    send(AppEvent::Moved(position.x, position.y));
+                },
+                Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size }, .. } => {
+                    send(AppEvent::ScaleFactor(scale_factor));
+                    send(AppEvent::Resized(new_inner_size.width, new_inner_size.height));
+                },
+                Event::WindowEvent { event: WindowEvent::DroppedFile(path), .. } => {
+                    send(AppEvent::FileDropped(path));
+                },
                 Event::WindowEvent { event: WindowEvent::Focused(f), .. } => {
                     window.focused = f;
+                    send(AppEvent::Focused(f));
                 },
                 _ => ()
             };
+
+            // The app may also decide to quit on its own, e.g. from the Quit binding
+            if app_handle.as_ref().map_or(false, |h| h.is_finished()) {
+                quit = true;
+            }
+
+            // Let the app thread finish its frame and idle the GPU before the window goes away
+            if quit {
+                send(AppEvent::Quit);
+
+                // A panicking app thread still fails the process
+                let exit_code = app_handle.take().map_or(0, |h| h.join().unwrap_or(1));
+
+                *control_flow = ControlFlow::ExitWithCode(exit_code);
+            }
         });
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,38 @@
+use clap::ValueEnum;
+use vrg::math::vec::Vec3;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
+pub enum InstanceLayout {
+    // Cube filled row by row, the last layer may be partial
+    Grid,
+    // Along +x
+    Line,
+    // Scattered through a cube sized to keep the same density as the grid
+    Random,
+}
+
+impl InstanceLayout {
+    pub fn positions(&self, count: usize, spacing: f32) -> Vec<Vec3> {
+        let side = (count as f32).cbrt().ceil() as usize;
+
+        match self {
+            InstanceLayout::Grid => (0..count).map(|i| {
+                let (x, y, z) = (i / (side * side), (i / side) % side, i % side);
+                Vec3::new(x as f32 * spacing, y as f32 * spacing, z as f32 * spacing)
+            }).collect(),
+            InstanceLayout::Line => (0..count).map(|i| Vec3::new(i as f32 * spacing, 0.0, 0.0)).collect(),
+            InstanceLayout::Random => {
+                // Fixed seed so runs are comparable
+                let mut state: u32 = 0x9e3779b9;
+                let mut next = || {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as f32 / u32::MAX as f32 * side as f32 * spacing
+                };
+
+                (0..count).map(|_| Vec3::new(next(), next(), next())).collect()
+            },
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::fs;
 use std::io::Read;
 
 use ash::vk;
+use serde::{Deserialize, Serialize};
 use vrg::math::vec::Vec3;
 use vrg::math::vec::Vec4;
 use vrg::vertex_buffer::VertexAttribute;
@@ -28,6 +29,27 @@ pub struct Mesh {
     pub indices: Vec<u32>,
 }
 
+// How the mesh shader colours fragments, passed in the push constant. Values must match res/shaders/src/shading.glsl.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ShadingMode {
+    Normals = 0,
+    // Single directional light
+    Lit = 1,
+    Unlit = 2,
+}
+
+impl ShadingMode {
+    pub const ALL: [ShadingMode; 3] = [ShadingMode::Normals, ShadingMode::Lit, ShadingMode::Unlit];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadingMode::Normals => "Normals",
+            ShadingMode::Lit => "Lit",
+            ShadingMode::Unlit => "Unlit",
+        }
+    }
+}
+
 impl FromObjTri for Tri {
     fn from_obj_tri(tri: Tri) -> Tri {
         tri
@@ -56,11 +78,25 @@ impl Tri {
 
 impl Mesh {
     // TODO: Reuse tris
-    pub fn from_obj(path: &str) -> Mesh {
+    pub fn from_obj(path: &str) -> Result<Mesh, String> {
+        let mut tris = Vec::<Tri>::new();
+
+        parse_obj_as_tris(&mut tris, path)?;
+
+        Ok(Mesh::from_tris(&tris))
+    }
+
+    // For OBJ data that isn't a plain file, e.g. from an archive. Name is only used in errors.
+    pub fn from_obj_bytes(name: &str, data: &[u8]) -> Result<Mesh, String> {
+        let raw = std::str::from_utf8(data).map_err(|_| format!("\"{}\": not valid UTF-8", name))?;
         let mut tris = Vec::<Tri>::new();
 
-        parse_obj_as_tris(&mut tris, path);
+        parse_obj_str(&mut tris, raw).map_err(|e| format!("\"{}\": {}", name, e))?;
 
+        Ok(Mesh::from_tris(&tris))
+    }
+
+    fn from_tris(tris: &[Tri]) -> Mesh {
         let mut mesh = Mesh {
             verts: Vec::with_capacity(tris.len() * 3),
             indices: Vec::with_capacity(tris.len() * 3),
@@ -86,20 +122,25 @@ enum ObjParserState {
     Faces(usize, usize),
 }
 
-pub fn parse_obj_as_tris<T: FromObjTri>(tris: &mut Vec<T>, name: &str) {
-    let mut file = fs::File::open(name).expect(&format!("Error: File \"{}\" not found", name));
+pub fn parse_obj_as_tris<T: FromObjTri>(tris: &mut Vec<T>, name: &str) -> Result<(), String> {
+    let mut file = fs::File::open(name).map_err(|e| format!("Couldn't open \"{}\": {}", name, e))?;
     let mut raw = String::new();
-    file.read_to_string(&mut raw).unwrap();
+    file.read_to_string(&mut raw).map_err(|e| format!("Couldn't read \"{}\": {}", name, e))?;
+
+    parse_obj_str(tris, &raw).map_err(|e| format!("\"{}\": {}", name, e))
+}
 
+// Errors on coordinates that aren't numbers and faces that index verts that don't exist
+pub fn parse_obj_str<T: FromObjTri>(tris: &mut Vec<T>, raw: &str) -> Result<(), String> {
     let mut state = ObjParserState::Inactive;
 
     // TODO: Reserve required space beforehand
     let mut vs: Vec<[f32; 3]> = vec![];
     let mut fs: Vec<[[f32; 3]; 3]> = Vec::new();
 
-    let mut i: usize = 0;
     let mut c_prev: char = 0 as char;
-    for c in raw.chars() {
+    // Byte offsets, so slicing stays on char boundaries in files with non-ASCII comments
+    for (i, c) in raw.char_indices() {
         match state {
             ObjParserState::Inactive => {
                 if c == 'v' && c_prev == 10 as char {
@@ -118,15 +159,15 @@ pub fn parse_obj_as_tris<T: FromObjTri>(tris: &mut Vec<T>, name: &str) {
                     if start != 0 {
                         let v = vs.last_mut().unwrap();
 
-                        v[count] = raw[start..i].parse::<f32>().unwrap();
+                        v[count] = raw[start..i].parse::<f32>().map_err(|_| format!("invalid vertex coordinate \"{}\"", &raw[start..i]))?;
 
                         if count == 2 {
                             state = ObjParserState::Inactive;
                         } else {
-                            state = ObjParserState::Verts(count + 1, i + 1);
+                            state = ObjParserState::Verts(count + 1, i + c.len_utf8());
                         }
                     } else {
-                        state = ObjParserState::Verts(count, i + 1);
+                        state = ObjParserState::Verts(count, i + c.len_utf8());
                     }
                 }
             }
@@ -134,22 +175,22 @@ pub fn parse_obj_as_tris<T: FromObjTri>(tris: &mut Vec<T>, name: &str) {
                 if char::is_whitespace(c) {
                     if start != 0 {
                         let f = fs.last_mut().unwrap();
-                        let vi = raw[start..i].parse::<usize>().unwrap();
+                        let index = &raw[start..i];
+                        let vi = index.parse::<usize>().map_err(|_| format!("invalid face index \"{}\"", index))?;
 
-                        f[count] = vs[vi - 1];
+                        f[count] = *vi.checked_sub(1).and_then(|vi| vs.get(vi)).ok_or(format!("face index {} is out of range", vi))?;
 
                         if count == 2 {
                             state = ObjParserState::Inactive;
                         } else {
-                            state = ObjParserState::Faces(count + 1, i + 1);
+                            state = ObjParserState::Faces(count + 1, i + c.len_utf8());
                         }
                     } else {
-                        state = ObjParserState::Faces(count, i + 1);
+                        state = ObjParserState::Faces(count, i + c.len_utf8());
                     }
                 }
             }
         }
-        i += 1;
         c_prev = c;
     }
 
@@ -159,4 +200,21 @@ pub fn parse_obj_as_tris<T: FromObjTri>(tris: &mut Vec<T>, name: &str) {
 
         tris.push(formatted_tri);
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_obj_is_an_error() {
+        let tri = "\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        assert_eq!(Mesh::from_obj_bytes("tri", tri.as_bytes()).unwrap().indices.len(), 3);
+
+        assert!(Mesh::from_obj_bytes("bad vertex", b"\nv 0 x 0\n").is_err());
+        assert!(Mesh::from_obj_bytes("out of range", b"\nv 0 0 0\nf 1 2 3\n").is_err());
+        assert!(Mesh::from_obj_bytes("zero index", b"\nv 0 0 0\nf 0 1 1\n").is_err());
+        assert!(Mesh::from_obj_bytes("not utf-8", &[b'\n', b'v', b' ', 0xff, b'\n']).is_err());
+    }
+}
@@ -1 +1,3 @@
-pub mod mesh;
\ No newline at end of file
+pub mod layout;
+pub mod mesh;
+pub mod transform;
\ No newline at end of file
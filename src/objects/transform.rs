@@ -0,0 +1,160 @@
+use std::f32::consts::FRAC_PI_2;
+
+use vrg::math::{mat::Mat4, vec::{Vec3, Vec4}};
+
+// Keeps the view direction away from the poles, where Mat4::view degenerates
+pub const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[derive(Copy, Clone)]
+pub struct Transform {
+    pub pos: Vec3,
+    pub rot: Quat,
+    pub scale: Vec3,
+}
+
+impl Quat {
+    pub fn identity() -> Quat {
+        Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (s, c) = (angle * 0.5).sin_cos();
+
+        Quat { x: axis.x * s, y: axis.y * s, z: axis.z * s, w: c }
+    }
+
+    // Yaw around y, then pitch around the local x axis, then roll around the local z axis. Positive
+    // pitch looks up, matching the angles used by the cameras.
+    pub fn from_euler(pitch: f32, yaw: f32, roll: f32) -> Quat {
+        let yaw = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), yaw);
+        let pitch = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), -pitch);
+        let roll = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), roll);
+
+        yaw.mul(pitch).mul(roll)
+    }
+
+    pub fn mul(self, o: Quat) -> Quat {
+        Quat {
+            x: self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            y: self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            z: self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+            w: self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+        }
+    }
+
+    pub fn dot(self, o: Quat) -> f32 {
+        self.x * o.x + self.y * o.y + self.z * o.z + self.w * o.w
+    }
+
+    pub fn normalize(self) -> Quat {
+        let len = self.dot(self).sqrt();
+
+        Quat { x: self.x / len, y: self.y / len, z: self.z / len, w: self.w / len }
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = Vec3::cross(q, v);
+        let t = Vec3::new(t.x * 2.0, t.y * 2.0, t.z * 2.0);
+        let u = Vec3::cross(q, t);
+
+        Vec3::new(v.x + t.x * self.w + u.x, v.y + t.y * self.w + u.y, v.z + t.z * self.w + u.z)
+    }
+
+    // Normalized lerp along the shortest arc
+    pub fn nlerp(self, o: Quat, t: f32) -> Quat {
+        let o = if self.dot(o) < 0.0 { Quat { x: -o.x, y: -o.y, z: -o.z, w: -o.w } } else { o };
+
+        Quat {
+            x: self.x + (o.x - self.x) * t,
+            y: self.y + (o.y - self.y) * t,
+            z: self.z + (o.z - self.z) * t,
+            w: self.w + (o.w - self.w) * t,
+        }.normalize()
+    }
+}
+
+impl Transform {
+    pub fn new(pos: Vec3, rot: Quat) -> Transform {
+        Transform {
+            pos,
+            rot,
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn from_pos(pos: Vec3) -> Transform {
+        Transform::new(pos, Quat::identity())
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rot.rotate(Vec3::new(0.0, 0.0, 1.0))
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rot.rotate(Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    pub fn lerp(&self, o: &Transform, t: f32) -> Transform {
+        Transform {
+            pos: lerp(self.pos, o.pos, t),
+            rot: self.rot.nlerp(o.rot, t),
+            scale: lerp(self.scale, o.scale, t),
+        }
+    }
+
+    // Model matrix in the layout the mesh shader reads instance transforms in
+    pub fn to_mat(&self) -> Mat4 {
+        let x = self.rot.rotate(Vec3::new(self.scale.x, 0.0, 0.0));
+        let y = self.rot.rotate(Vec3::new(0.0, self.scale.y, 0.0));
+        let z = self.rot.rotate(Vec3::new(0.0, 0.0, self.scale.z));
+
+        let mut mat = Mat4::identity();
+        mat.x = Vec4::new(x.x, x.y, x.z, 0.0);
+        mat.y = Vec4::new(y.x, y.y, y.z, 0.0);
+        mat.z = Vec4::new(z.x, z.y, z.z, 0.0);
+        mat.w = Vec4::new(self.pos.x, self.pos.y, self.pos.z, 1.0);
+
+        mat
+    }
+
+    // View matrix looking along forward(), rolled so that up() points up on screen
+    pub fn view_mat(&self) -> Mat4 {
+        let dir = self.forward();
+        let up = self.up();
+
+        // The up vector Mat4::view picks for this direction, without any roll
+        let right = Vec3::cross(Vec3::new(0.0, 1.0, 0.0), dir).normalize();
+        let level_up = Vec3::cross(dir, right);
+
+        let roll = dot(Vec3::cross(level_up, up), dir).atan2(dot(level_up, up));
+        if roll.abs() < 1e-5 {
+            return Mat4::view(dir, self.pos);
+        }
+
+        let (s, c) = roll.sin_cos();
+        let mut roll_mat = Mat4::identity();
+        roll_mat.x.x = c;
+        roll_mat.x.y = s;
+        roll_mat.y.x = -s;
+        roll_mat.y.y = c;
+
+        Mat4::view(dir, self.pos) * roll_mat
+    }
+}
+
+pub fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    Vec3::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t, a.z + (b.z - a.z) * t)
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
@@ -0,0 +1,192 @@
+use imgui::{Drag, Ui};
+
+use crate::{camera::{projection::{Projection, ProjectionMode}, CameraMode}, controller::Controller, input::{Action, Input}, objects::mesh::ShadingMode, profiler::{self, Stats}, render_stats::{self, RenderStats}, settings::{self, Settings}};
+
+pub fn interface_panel(ui: &Ui, ui_scale: &mut f32) {
+    ui.window("Interface").build(|| {
+        ui.slider_config("UI scale", settings::GUI_SCALE.0, settings::GUI_SCALE.1).display_format("%.2f").build(ui_scale);
+    });
+}
+
+pub fn camera_panel(ui: &Ui, controller: &mut Controller) {
+    ui.window("Camera").build(|| {
+        let names = CameraMode::ALL.map(|m| m.name());
+        let mut mode = CameraMode::ALL.iter().position(|m| *m == controller.mode()).unwrap_or(0);
+
+        if ui.combo_simple_string("Mode", &mut mode, &names) {
+            controller.set_mode(CameraMode::ALL[mode]);
+        }
+    });
+}
+
+pub fn projection_panel(ui: &Ui, projection: &mut Projection) {
+    ui.window("Projection").build(|| {
+        let mut mode = projection.mode as usize;
+        if ui.combo_simple_string("Mode", &mut mode, &["Perspective", "Orthographic"]) {
+            projection.mode = ProjectionMode::ALL[mode];
+        }
+
+        let mut fov = projection.fov.to_degrees();
+        if ui.slider_config("FOV", settings::FOV_DEGREES.0, settings::FOV_DEGREES.1).display_format("%.1f").build(&mut fov) {
+            projection.fov = fov.to_radians();
+        }
+
+        Drag::new("Near").range(0.0001, projection.far).speed(0.0001).display_format("%.4f").build(ui, &mut projection.near);
+        Drag::new("Far").range(projection.near, 100000.0).speed(0.5).display_format("%.1f").build(ui, &mut projection.far);
+        Drag::new("Ortho height").range(0.1, 1000.0).speed(0.1).display_format("%.1f").build(ui, &mut projection.ortho_height);
+    });
+}
+
+pub fn controls_panel(ui: &Ui, input: &mut Input) {
+    ui.window("Controls").build(|| {
+        Drag::new("Sensitivity").range(settings::MOUSE_SENSITIVITY.0, settings::MOUSE_SENSITIVITY.1).speed(0.00001).display_format("%.5f").build(ui, &mut input.sensitivity);
+        ui.checkbox("Invert Y", &mut input.invert_y);
+        ui.separator();
+
+        for action in Action::ALL {
+            let bound = match input.rebinding {
+                Some(a) if a == action => "Press a key...".to_string(),
+                _ => input.bindings.get(&action).map(|b| b.iter().map(Input::binding_name).collect::<Vec<_>>().join(", ")).unwrap_or_default(),
+            };
+
+            ui.text(format!("{:?}", action));
+            ui.same_line_with_pos(150.0);
+            // The ## suffix keeps button IDs unique when two actions share a binding
+            if ui.button(format!("{}##{:?}", if bound.is_empty() { "Unbound" } else { &bound }, action)) {
+                input.rebinding = Some(action);
+            }
+        }
+
+        ui.separator();
+        if ui.button("Save") {
+            input.save();
+        }
+    });
+}
+
+// Frames taking longer than this multiple of the median are drawn in red
+const SPIKE_FACTOR: f32 = 2.0;
+
+pub fn profiler_panel(ui: &Ui) {
+    ui.window("Profiler").build(|| {
+        profiler::with(|p| {
+            let frame_stats = match Stats::from_history(&p.frame_times) {
+                Some(stats) => stats,
+                None => return,
+            };
+
+            ui.text(format!("{:.2} ms ({:.0} fps), 1% low {:.2} ms ({:.0} fps)", frame_stats.avg, 1000.0 / frame_stats.avg, frame_stats.low_1, 1000.0 / frame_stats.low_1));
+
+            // Bar per frame scaled to the slowest one in the history
+            let [w, _] = ui.content_region_avail();
+            let h = 80.0;
+            let [x, y] = ui.cursor_screen_pos();
+            let draw_list = ui.get_window_draw_list();
+            let bar_w = w / p.frame_times.len() as f32;
+            let spike = frame_stats.p50 * SPIKE_FACTOR;
+
+            draw_list.add_rect([x, y], [x + w, y + h], [0.1, 0.1, 0.1, 0.8]).filled(true).build();
+            for (i, ms) in p.frame_times.iter().enumerate() {
+                // A history of zeros would otherwise divide by zero
+                let bar_h = if frame_stats.max > 0.0 { h * ms / frame_stats.max } else { 0.0 };
+                let col = if *ms > spike { [0.9, 0.2, 0.2, 1.0] } else { [0.3, 0.7, 0.3, 1.0] };
+                let bx = x + i as f32 * bar_w;
+
+                draw_list.add_rect([bx, y + h - bar_h], [bx + bar_w.max(1.0), y + h], col).filled(true).build();
+            }
+            ui.dummy([w, h]);
+
+            if let Some(_table) = ui.begin_table_header("sections", [
+                "Section",
+                "Last",
+                "Min",
+                "Avg",
+                "Max",
+                "P95",
+                "P99",
+                "1% low",
+            ].map(imgui::TableColumnSetup::new)) {
+                for (path, section) in &p.sections {
+                    let stats = match Stats::from_history(&section.history) {
+                        Some(stats) => stats,
+                        None => continue,
+                    };
+                    let name = path.rsplit('/').next().unwrap_or(path);
+
+                    ui.table_next_column();
+                    ui.text(format!("{}{}", "  ".repeat(section.depth), name));
+                    for v in [stats.last, stats.min, stats.avg, stats.max, stats.p95, stats.p99, stats.low_1] {
+                        ui.table_next_column();
+                        ui.text(format!("{:.3}", v));
+                    }
+                }
+            }
+
+            if ui.collapsing_header("Section history", imgui::TreeNodeFlags::empty()) {
+                for (path, section) in &p.sections {
+                    let history: Vec<f32> = section.history.iter().copied().collect();
+                    let last = history.last().copied().unwrap_or(0.0);
+                    let name = path.rsplit('/').next().unwrap_or(path);
+
+                    // The ## suffix keeps plot IDs unique when sections in different scopes share a name
+                    ui.plot_lines(format!("{}{}##{}", "  ".repeat(section.depth), name, path), &history)
+                        .graph_size([w * 0.7, 40.0])
+                        .scale_min(0.0)
+                        .overlay_text(format!("{:.3} ms", last))
+                        .build();
+                }
+            }
+        });
+    });
+}
+
+pub fn render_stats_panel(ui: &Ui, stats: &RenderStats) {
+    ui.window("Render stats").build(|| {
+        ui.text(format!("Draw calls: {}", stats.draw_calls));
+        ui.text(format!("Instances: {} submitted, {} visible", stats.instances, stats.visible_instances));
+        ui.text(format!("Triangles: {}", stats.triangles));
+        ui.text(format!("Vertices: {}", stats.vertices));
+        ui.separator();
+
+        ui.text(format!("GUI: {} draws, {} vertices, {} indices", stats.gui_draw_calls, stats.gui_vertices, stats.gui_indices));
+        ui.separator();
+
+        ui.text(format!("Swapchain: {}x{}", stats.resolution.0, stats.resolution.1));
+        ui.separator();
+
+        ui.text(format!("Memory: {}", render_stats::format_bytes(stats.total_memory())));
+        for (name, bytes) in &stats.memory {
+            ui.bullet_text(format!("{}: {}", name, render_stats::format_bytes(*bytes)));
+        }
+    });
+}
+
+// Returns true if anything was edited
+pub fn settings_panel(ui: &Ui, settings: &mut Settings) -> bool {
+    let mut changed = false;
+
+    ui.window("Settings").build(|| {
+        changed |= Drag::new("Camera speed").range(settings::CAMERA_SPEED.0, settings::CAMERA_SPEED.1).speed(0.1).display_format("%.1f").build(ui, &mut settings.camera_speed);
+        changed |= Drag::new("Mouse sensitivity").range(settings::MOUSE_SENSITIVITY.0, settings::MOUSE_SENSITIVITY.1).speed(0.00001).display_format("%.5f").build(ui, &mut settings.mouse_sensitivity);
+        changed |= ui.slider_config("FOV", settings::FOV_DEGREES.0, settings::FOV_DEGREES.1).display_format("%.1f").build(&mut settings.fov_degrees);
+        changed |= ui.color_edit4("Clear color", &mut settings.clear_color);
+
+        let names = ShadingMode::ALL.map(|m| m.name());
+        let mut shading = ShadingMode::ALL.iter().position(|m| *m == settings.shading).unwrap_or(0);
+        if ui.combo_simple_string("Shading", &mut shading, &names) {
+            settings.shading = ShadingMode::ALL[shading];
+            changed = true;
+        }
+
+        changed |= ui.slider_config("GUI scale", settings::GUI_SCALE.0, settings::GUI_SCALE.1).display_format("%.2f").build(&mut settings.gui_scale);
+
+        ui.separator();
+        ui.text(format!("Window: {}x{}", settings.window.width, settings.window.height));
+        if let Some(scene) = &settings.last_scene {
+            ui.text(format!("Scene: {}", scene.display()));
+        }
+        ui.text_disabled(format!("Saved to {}", Settings::path().display()));
+    });
+
+    changed
+}
@@ -0,0 +1,163 @@
+use std::{cell::RefCell, collections::{HashMap, VecDeque}, time::Instant};
+
+// Frames of history kept per section
+const HISTORY: usize = 600;
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+pub struct Section {
+    // Nesting depth, 0 for top level scopes
+    pub depth: usize,
+    // Milliseconds per frame, oldest first
+    pub history: VecDeque<f32>,
+    frame_total: f32,
+}
+
+pub struct Stats {
+    pub last: f32,
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    // Average of the slowest 1% of frames
+    pub low_1: f32,
+}
+
+pub struct Profiler {
+    // Sections by full path, e.g. "Draw/Gui", in first seen order
+    pub sections: Vec<(String, Section)>,
+    indices: HashMap<String, usize>,
+
+    pub frame_times: VecDeque<f32>,
+    frame_start: Option<Instant>,
+
+    stack: Vec<String>,
+}
+
+// Times everything until it's dropped. Scopes opened while another is alive are nested under it.
+pub struct Scope {
+    start: Instant,
+}
+
+pub fn scope(name: &str) -> Scope {
+    PROFILER.with(|p| {
+        let mut p = p.borrow_mut();
+        let path = match p.stack.last() {
+            Some(parent) => format!("{}/{}", parent, name),
+            None => name.to_string(),
+        };
+
+        // Registered on open so parents are listed before their children
+        let depth = p.stack.len();
+        p.section_mut(&path, depth);
+        p.stack.push(path);
+    });
+
+    Scope {
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let ms = self.start.elapsed().as_secs_f32() * 1000.0;
+
+        PROFILER.with(|p| {
+            let mut p = p.borrow_mut();
+            if let Some(path) = p.stack.pop() {
+                let depth = p.stack.len();
+                p.section_mut(&path, depth).frame_total += ms;
+            }
+        });
+    }
+}
+
+pub fn begin_frame() {
+    PROFILER.with(|p| p.borrow_mut().begin_frame());
+}
+
+pub fn end_frame() {
+    PROFILER.with(|p| p.borrow_mut().end_frame());
+}
+
+pub fn with<R, F: FnOnce(&Profiler) -> R>(f: F) -> R {
+    PROFILER.with(|p| f(&p.borrow()))
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            sections: Vec::new(),
+            indices: HashMap::new(),
+
+            frame_times: VecDeque::with_capacity(HISTORY),
+            frame_start: None,
+
+            stack: Vec::new(),
+        }
+    }
+
+    fn section_mut(&mut self, path: &str, depth: usize) -> &mut Section {
+        let i = match self.indices.get(path) {
+            Some(i) => *i,
+            None => {
+                self.sections.push((path.to_string(), Section { depth, history: VecDeque::with_capacity(HISTORY), frame_total: 0.0 }));
+                self.indices.insert(path.to_string(), self.sections.len() - 1);
+                self.sections.len() - 1
+            },
+        };
+
+        &mut self.sections[i].1
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = Some(Instant::now());
+    }
+
+    // Pushes this frame's totals into the history, sections that didn't run count as 0
+    pub fn end_frame(&mut self) {
+        if let Some(start) = self.frame_start.take() {
+            push(&mut self.frame_times, start.elapsed().as_secs_f32() * 1000.0);
+        }
+
+        for (_, section) in &mut self.sections {
+            let total = section.frame_total;
+            push(&mut section.history, total);
+            section.frame_total = 0.0;
+        }
+    }
+}
+
+impl Stats {
+    pub fn from_history(history: &VecDeque<f32>) -> Option<Stats> {
+        let last = *history.back()?;
+
+        let mut sorted: Vec<f32> = history.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let percentile = |p: f32| sorted[((sorted.len() - 1) as f32 * p).round() as usize];
+        let slowest = (sorted.len() / 100).max(1);
+
+        Some(Stats {
+            last,
+            min: sorted[0],
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            max: sorted[sorted.len() - 1],
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            low_1: sorted[sorted.len() - slowest..].iter().sum::<f32>() / slowest as f32,
+        })
+    }
+}
+
+fn push(history: &mut VecDeque<f32>, v: f32) {
+    if history.len() == HISTORY {
+        history.pop_front();
+    }
+    history.push_back(v);
+}
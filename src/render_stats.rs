@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use vrg::math::{mat::Mat4, vec::{Vec3, Vec4}};
+
+// Counts gathered on the CPU side from what's handed to the renderer
+pub struct RenderStats {
+    pub draw_calls: usize,
+    pub instances: usize,
+    // Instances whose bounding sphere is at least partly inside the view frustum
+    pub visible_instances: usize,
+    pub triangles: usize,
+    pub vertices: usize,
+
+    pub gui_draw_calls: usize,
+    pub gui_vertices: usize,
+    pub gui_indices: usize,
+
+    // Bytes requested per buffer or image, by name
+    pub memory: BTreeMap<String, usize>,
+
+    pub resolution: (u32, u32),
+}
+
+impl RenderStats {
+    pub fn new() -> RenderStats {
+        RenderStats {
+            draw_calls: 0,
+            instances: 0,
+            visible_instances: 0,
+            triangles: 0,
+            vertices: 0,
+
+            gui_draw_calls: 0,
+            gui_vertices: 0,
+            gui_indices: 0,
+
+            memory: BTreeMap::new(),
+
+            resolution: (0, 0),
+        }
+    }
+
+    pub fn track(&mut self, name: &str, bytes: usize) {
+        self.memory.insert(name.to_string(), bytes);
+    }
+
+    pub fn total_memory(&self) -> usize {
+        self.memory.values().sum()
+    }
+}
+
+// Tests each instance's bounding sphere against the side planes and the camera plane in clip space. Matrices follow the row/column
+// layout described on Projection::mat.
+pub fn count_visible(view_proj: &Mat4, proj: &Mat4, positions: &[Vec3], radius: f32) -> usize {
+    let row = |r: &Vec4, p: &Vec3| r.x * p.x + r.y * p.y + r.z * p.z + r.w;
+
+    // The sphere's extent in clip space, roughly, ignoring the perspective divide
+    let margin_x = radius * proj.x.x.abs();
+    let margin_y = radius * proj.y.y.abs();
+    let margin_w = radius * proj.w.z.abs();
+
+    positions.iter().filter(|p| {
+        let x = row(&view_proj.x, p);
+        let y = row(&view_proj.y, p);
+        let w = row(&view_proj.w, p);
+
+        w > -margin_w && x.abs() <= w + margin_x && y.abs() <= w + margin_y
+    }).count()
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.2} GiB", b as f32 / (1 << 30) as f32),
+        b if b >= 1 << 20 => format!("{:.2} MiB", b as f32 / (1 << 20) as f32),
+        b if b >= 1 << 10 => format!("{:.2} KiB", b as f32 / (1 << 10) as f32),
+        b => format!("{} B", b),
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fs::File, io::{BufRead, BufReader, BufWriter, Write}, time::Instant};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::{camera::CameraMode, event::AppEvent, input::InputConfig};
+
+// The subset of AppEvent that affects simulation, plus state changed through the GUI, so both need
+// to be reproduced
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    Key { key: VirtualKeyCode, state: ElementState },
+    MouseButton { button: MouseButton, state: ElementState },
+    MouseMotion(f32, f32),
+    Wheel(f32),
+    Focused(bool),
+    CameraMode(CameraMode),
+    // Settings the controller and input read each frame
+    Controls { speed: f32, sensitivity: f32, invert_y: bool },
+    // Whether ImGui wanted the keyboard and mouse after its last update, which decides what reaches the camera
+    GuiCapture { keyboard: bool, mouse: bool },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimedEvent {
+    // Seconds since recording started
+    pub time: f64,
+    pub event: RecordedEvent,
+}
+
+// First line of a recording. Bindings and axis scales decide what the recorded events do, so replays use
+// these rather than the local input.toml.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub input: InputConfig,
+}
+
+// Everything that happened before a frame's update, written as one JSON line per frame
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub time: f64,
+    pub delta: f32,
+    pub events: Vec<TimedEvent>,
+}
+
+pub struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+    pending: Vec<TimedEvent>,
+}
+
+pub struct Replay {
+    pub header: RecordingHeader,
+    frames: Vec<RecordedFrame>,
+    next: usize,
+}
+
+impl RecordedEvent {
+    pub fn from_app_event(event: &AppEvent) -> Option<RecordedEvent> {
+        match event {
+            AppEvent::Key { key, state } => Some(RecordedEvent::Key { key: *key, state: *state }),
+            AppEvent::MouseButton { button, state } => Some(RecordedEvent::MouseButton { button: *button, state: *state }),
+            AppEvent::MouseMotion(d) => Some(RecordedEvent::MouseMotion(d.x, d.y)),
+            AppEvent::Wheel(d) => Some(RecordedEvent::Wheel(*d)),
+            AppEvent::Focused(f) => Some(RecordedEvent::Focused(*f)),
+            _ => None,
+        }
+    }
+}
+
+impl Recorder {
+    pub fn new(path: &str, header: RecordingHeader) -> Recorder {
+        let file = File::create(path).expect(&format!("Error: Couldn't create recording \"{}\"", path));
+        let mut file = BufWriter::new(file);
+
+        let line = serde_json::to_string(&header).expect("Error serializing recording header");
+        writeln!(file, "{}", line).expect("Error writing recording");
+
+        Recorder {
+            file,
+            start: Instant::now(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: &AppEvent) {
+        if let Some(event) = RecordedEvent::from_app_event(event) {
+            self.push(event);
+        }
+    }
+
+    pub fn push(&mut self, event: RecordedEvent) {
+        self.pending.push(TimedEvent { time: self.start.elapsed().as_secs_f64(), event });
+    }
+
+    pub fn end_frame(&mut self, delta: f32) {
+        let frame = RecordedFrame {
+            time: self.start.elapsed().as_secs_f64(),
+            delta,
+            events: std::mem::take(&mut self.pending),
+        };
+
+        let line = serde_json::to_string(&frame).expect("Error serializing recorded frame");
+        writeln!(self.file, "{}", line).expect("Error writing recording");
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let file = File::open(path).map_err(|e| format!("Couldn't open recording \"{}\": {}", path, e))?;
+
+        let mut lines = BufReader::new(file).lines();
+        let read_error = |e| format!("Couldn't read recording \"{}\": {}", path, e);
+
+        let header = lines.next().ok_or_else(|| format!("Recording \"{}\" is empty", path))?.map_err(read_error)?;
+        let header = serde_json::from_str(&header).map_err(|e| format!("Malformed header on line 1 of \"{}\": {}", path, e))?;
+
+        let mut frames = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(read_error)?;
+            let frame = serde_json::from_str(&line).map_err(|e| format!("Malformed frame on line {} of \"{}\": {}", i + 2, path, e))?;
+            frames.push(frame);
+        }
+
+        Ok(Replay {
+            header,
+            frames,
+            next: 0,
+        })
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let frame = self.frames.get(self.next).cloned();
+        self.next += 1;
+
+        frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, Binding};
+
+    #[test]
+    fn recording_keeps_its_input_mapping() {
+        let path = std::env::temp_dir().join(format!("rasterizer-replay-{}.jsonl", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+
+        let mut input = InputConfig::default();
+        input.bindings[0].inputs = vec![Binding::Key(VirtualKeyCode::Up)];
+
+        {
+            let mut recorder = Recorder::new(&path, RecordingHeader { input });
+            recorder.push(RecordedEvent::Key { key: VirtualKeyCode::Up, state: ElementState::Pressed });
+            recorder.end_frame(0.5);
+        }
+
+        let mut replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let binding = &replay.header.input.bindings[0];
+        assert_eq!(binding.action, Action::MoveForward);
+        assert_eq!(binding.inputs, vec![Binding::Key(VirtualKeyCode::Up)]);
+
+        let frame = replay.next_frame().unwrap();
+        assert_eq!(frame.delta, 0.5);
+        assert_eq!(frame.events[0].event, RecordedEvent::Key { key: VirtualKeyCode::Up, state: ElementState::Pressed });
+        assert!(replay.next_frame().is_none());
+    }
+}
@@ -0,0 +1,212 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, objects::mesh::ShadingMode};
+
+// Bumped whenever keys are renamed or change meaning, with a matching step in migrate
+const VERSION: u32 = 1;
+
+// Allowed ranges, shared with the panels that edit them
+pub const CAMERA_SPEED: (f32, f32) = (0.1, 1000.0);
+pub const MOUSE_SENSITIVITY: (f32, f32) = (0.00001, 0.01);
+pub const FOV_DEGREES: (f32, f32) = (10.0, 170.0);
+pub const GUI_SCALE: (f32, f32) = (0.5, 3.0);
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    // Physical pixels
+    pub width: u32,
+    pub height: u32,
+    // Left to the window manager if unset
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+// Everything the user can tweak that should survive a restart. Missing keys fall back to their defaults.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+
+    // Units per second
+    pub camera_speed: f32,
+    pub mouse_sensitivity: f32,
+    pub fov_degrees: f32,
+
+    pub clear_color: [f32; 4],
+    pub shading: ShadingMode,
+    pub gui_scale: f32,
+
+    pub last_scene: Option<PathBuf>,
+
+    // Last so the file stays valid TOML, tables have to come after plain keys
+    pub window: WindowSettings,
+}
+
+impl Default for WindowSettings {
+    fn default() -> WindowSettings {
+        WindowSettings {
+            width: 1280,
+            height: 720,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            version: VERSION,
+
+            camera_speed: 12.0,
+            mouse_sensitivity: 0.001,
+            fov_degrees: 90.0,
+
+            clear_color: [0.82, 0.8, 0.9, 1.0],
+            shading: ShadingMode::Normals,
+            gui_scale: 1.0,
+
+            last_scene: None,
+
+            window: WindowSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        config::config_dir().join("settings.toml")
+    }
+
+    // Falls back to the defaults if the file is missing, malformed or from a newer version
+    pub fn load() -> Settings {
+        let path = Settings::path();
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(_) => return Settings::default(),
+        };
+
+        let parsed = raw.parse::<toml::Table>().map_err(|e| e.to_string()).and_then(|mut table| {
+            let version = file_version(&table);
+            if version > VERSION {
+                return Err(format!("written by a newer version ({} > {})", version, VERSION));
+            }
+
+            migrate(&mut table);
+            table.try_into::<Settings>().map_err(|e| e.to_string())
+        });
+
+        match parsed {
+            Ok(settings) => settings.clamped(),
+            Err(e) => {
+                println!("Error: Couldn't load settings \"{}\", using defaults: {}", path.display(), e);
+                Settings::default()
+            },
+        }
+    }
+
+    pub fn save(&self) {
+        let path = Settings::path();
+
+        // A newer version's file was loaded as defaults, overwriting it would throw its settings away
+        let existing = fs::read_to_string(&path).ok().and_then(|raw| raw.parse::<toml::Table>().ok());
+        if existing.is_some_and(|table| file_version(&table) > VERSION) {
+            return;
+        }
+
+        let raw = toml::to_string_pretty(self).expect("Error serializing settings");
+
+        if let Err(e) = fs::write(&path, raw) {
+            println!("Error: Couldn't save settings to \"{}\": {}", path.display(), e);
+        }
+    }
+
+    // Hand edited values outside what the panels allow are pulled back in range
+    fn clamped(mut self) -> Settings {
+        let defaults = Settings::default();
+        let clamp = |v: f32, (min, max): (f32, f32), default: f32| if v.is_nan() { default } else { v.clamp(min, max) };
+
+        self.camera_speed = clamp(self.camera_speed, CAMERA_SPEED, defaults.camera_speed);
+        self.mouse_sensitivity = clamp(self.mouse_sensitivity, MOUSE_SENSITIVITY, defaults.mouse_sensitivity);
+        self.fov_degrees = clamp(self.fov_degrees, FOV_DEGREES, defaults.fov_degrees);
+        self.gui_scale = clamp(self.gui_scale, GUI_SCALE, defaults.gui_scale);
+
+        if self.window.width == 0 || self.window.height == 0 {
+            self.window.width = defaults.window.width;
+            self.window.height = defaults.window.height;
+        }
+
+        self
+    }
+}
+
+// Files from before settings were versioned have no version key
+fn file_version(table: &toml::Table) -> u32 {
+    table.get("version").and_then(|v| v.as_integer()).unwrap_or(0) as u32
+}
+
+// Upgrades a table written by an older version in place, one version at a time
+fn migrate(table: &mut toml::Table) {
+    let mut version = file_version(table);
+
+    while version < VERSION {
+        match version {
+            0 => {
+                // Keys were named after the fields they came from, with the FOV in radians
+                rename(table, "sensitivity", "mouse_sensitivity");
+                rename(table, "ui_scale", "gui_scale");
+                if let Some(fov) = table.remove("fov").and_then(|v| v.as_float()) {
+                    table.insert("fov_degrees".to_string(), toml::Value::Float(fov.to_degrees()));
+                }
+            },
+            _ => unreachable!(),
+        }
+
+        version += 1;
+    }
+
+    table.insert("version".to_string(), toml::Value::Integer(VERSION as i64));
+}
+
+fn rename(table: &mut toml::Table, from: &str, to: &str) {
+    if let Some(v) = table.remove(from) {
+        table.insert(to.to_string(), v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let settings = Settings {
+            camera_speed: 0.0,
+            mouse_sensitivity: f32::NAN,
+            fov_degrees: 400.0,
+            gui_scale: 10.0,
+            window: WindowSettings { width: 0, height: 600, x: None, y: None },
+            ..Settings::default()
+        }.clamped();
+
+        assert_eq!(settings.camera_speed, CAMERA_SPEED.0);
+        assert_eq!(settings.mouse_sensitivity, Settings::default().mouse_sensitivity);
+        assert_eq!(settings.fov_degrees, FOV_DEGREES.1);
+        assert_eq!(settings.gui_scale, GUI_SCALE.1);
+        assert_eq!(settings.window, WindowSettings::default());
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let mut table: toml::Table = "sensitivity = 0.002\nfov = 1.0\n".parse().unwrap();
+        migrate(&mut table);
+        let settings: Settings = table.try_into().unwrap();
+
+        assert_eq!(settings.version, VERSION);
+        assert_eq!(settings.mouse_sensitivity, 0.002);
+        assert!((settings.fov_degrees - 1.0f32.to_degrees()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fmt, fs, path::{Path, PathBuf}};
+
+use shaderc::{CompileOptions, Compiler, IncludeType, ResolvedInclude, ShaderKind};
+
+// Shared with build.rs and the pack tool through #[path], so this can only depend on std and shaderc
+
+// Deep enough for any sane include tree, shallow enough to catch a file including itself
+const MAX_INCLUDE_DEPTH: u32 = 32;
+
+// One compiler error or warning, pointing at the file it came from even when that's an include
+pub struct Diagnostic {
+    pub file: PathBuf,
+    // Missing for errors about the whole file, like a failed include
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+pub struct Compiled {
+    pub spirv: Vec<u8>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+// Stage from the extension, the same convention glslc uses. Anything else, e.g. .glsl, is only meant to be included.
+pub fn kind(path: &Path) -> Option<ShaderKind> {
+    let kind = match path.extension()?.to_str()? {
+        "vert" => ShaderKind::Vertex,
+        "frag" => ShaderKind::Fragment,
+        "comp" => ShaderKind::Compute,
+        "geom" => ShaderKind::Geometry,
+        "tesc" => ShaderKind::TessControl,
+        "tese" => ShaderKind::TessEvaluation,
+        _ => return None,
+    };
+
+    Some(kind)
+}
+
+// Compiles a GLSL file on disk. #include "file" is resolved relative to the including file, <file> relative to the root
+// file's directory.
+pub fn compile(path: &Path) -> Result<Compiled, Vec<Diagnostic>> {
+    let fail = |message: String| vec![Diagnostic { file: path.to_path_buf(), line: None, message }];
+
+    let kind = kind(path).ok_or_else(|| fail("unknown shader stage, expected .vert, .frag, .comp, .geom, .tesc or .tese".to_string()))?;
+    let source = fs::read_to_string(path).map_err(|e| fail(format!("couldn't read: {}", e)))?;
+
+    let compiler = Compiler::new().ok_or_else(|| fail("couldn't create the shader compiler".to_string()))?;
+    let mut options = CompileOptions::new().ok_or_else(|| fail("couldn't create the shader compiler options".to_string()))?;
+
+    let root_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    options.set_include_callback(move |requested, include_type, requesting, depth| {
+        if depth as u32 > MAX_INCLUDE_DEPTH {
+            return Err(format!("includes nested more than {} deep, is \"{}\" including itself?", MAX_INCLUDE_DEPTH, requested));
+        }
+
+        let dir = match include_type {
+            IncludeType::Relative => Path::new(requesting).parent().unwrap_or(Path::new(".")).to_path_buf(),
+            IncludeType::Standard => root_dir.clone(),
+        };
+        let resolved = dir.join(requested);
+
+        // The resolved name is what errors inside the include get reported against
+        match fs::read_to_string(&resolved) {
+            Ok(content) => Ok(ResolvedInclude { resolved_name: resolved.to_string_lossy().to_string(), content }),
+            Err(e) => Err(format!("couldn't read \"{}\": {}", resolved.display(), e)),
+        }
+    });
+
+    let name = path.to_string_lossy();
+    match compiler.compile_into_spirv(&source, kind, &name, "main", Some(&options)) {
+        Ok(artifact) => Ok(Compiled {
+            spirv: artifact.as_binary_u8().to_vec(),
+            warnings: parse_diagnostics(&artifact.get_warning_messages(), path),
+        }),
+        Err(shaderc::Error::CompilationError(_, messages)) => Err(parse_diagnostics(&messages, path)),
+        Err(e) => Err(fail(e.to_string())),
+    }
+}
+
+// glslang reports "file:line: error: message", or "file: error: message" when there's no line. File can contain ':' on
+// Windows, so the line is split off from the right.
+fn parse_diagnostics(messages: &str, path: &Path) -> Vec<Diagnostic> {
+    // glslang ends with a summary like "2 errors generated.", which isn't a diagnostic of its own
+    messages.lines().filter(|l| !l.trim().is_empty() && !is_summary(l)).map(|l| {
+        let split = l.find(": error: ").or_else(|| l.find(": warning: "));
+        let (location, message) = match split {
+            Some(i) => (&l[..i], l[i + 2..].to_string()),
+            None => return Diagnostic { file: path.to_path_buf(), line: None, message: l.to_string() },
+        };
+
+        match location.rsplit_once(':').and_then(|(file, line)| line.parse::<u32>().ok().map(|line| (file, line))) {
+            Some((file, line)) => Diagnostic { file: PathBuf::from(file), line: Some(line), message },
+            None => Diagnostic { file: PathBuf::from(location), line: None, message },
+        }
+    }).collect()
+}
+
+fn is_summary(line: &str) -> bool {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    matches!(words[..], [count, "error" | "errors" | "warning" | "warnings", "generated."] if count.parse::<u32>().is_ok())
+}